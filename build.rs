@@ -1,21 +1,176 @@
-use std::{env, path::Path};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Linking {
+    Static,
+    Dynamic,
+}
 
 fn main() {
-    let vendored = env::var("CARGO_FEATURE_VENDORED").is_ok();
-    match vendored {
-        true => {
-            let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-            let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
-            println!(
-                "cargo:rustc-link-search=native={}",
-                Path::new(&dir).join("qhyccd-sdk").join(arch).display()
-            );
+    println!("cargo:rerun-if-env-changed=QHYCCD_LIB_DIR");
+    println!("cargo:rerun-if-env-changed=QHYCCD_STATIC");
+    #[cfg(feature = "bindgen")]
+    generate_bindings();
+    if env::var("CARGO_FEATURE_RUNTIME_LINK").is_ok() {
+        return;
+    }
+
+    let requested = match env::var("QHYCCD_STATIC").as_deref() {
+        Ok("1") | Ok("true") => Some(Linking::Static),
+        Ok("0") | Ok("false") => Some(Linking::Dynamic),
+        _ => None,
+    };
+
+    let mut searched = Vec::new();
+    if let Ok(dir) = env::var("QHYCCD_LIB_DIR") {
+        let dir = PathBuf::from(dir);
+        if link_from(&dir, requested, false) {
+            return;
+        }
+        searched.push(format!("QHYCCD_LIB_DIR={}", dir.display()));
+    }
+
+    if env::var("CARGO_FEATURE_VENDORED").is_ok() {
+        let dir = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap())
+            .join("qhyccd-sdk")
+            .join(env::var("CARGO_CFG_TARGET_ARCH").unwrap());
+        if link_from(&dir, requested, true) {
+            return;
+        }
+        searched.push(format!("vendored {}", dir.display()));
+    }
+
+    match pkg_config::Config::new()
+        .statik(requested == Some(Linking::Static))
+        .probe("qhyccd")
+    {
+        Ok(_) => return,
+        Err(_) => searched.push("pkg-config qhyccd".to_string()),
+    }
+
+    for dir in ["/usr/local/lib", "/usr/lib"] {
+        if link_from(Path::new(dir), requested, false) {
+            return;
+        }
+        searched.push(dir.to_string());
+    }
+
+    panic!(
+        "libqhyccd not found, searched for libqhyccd.a, libqhyccd.so, libqhyccd.so.* and \
+         libqhyccd.dylib in:\n  {}\n\
+         Set QHYCCD_LIB_DIR to the directory of the QHYCCD SDK library, QHYCCD_STATIC=1 or \
+         QHYCCD_STATIC=0 to require static or dynamic linking, or PKG_CONFIG_PATH to the \
+         directory of qhyccd.pc. Alternatively copy the SDK library into qhyccd-sdk/<arch> and \
+         enable the `vendored` feature, or enable the `runtime-link` feature to load libqhyccd \
+         when the program starts.",
+        searched.join("\n  ")
+    );
+}
+
+/// Link libqhyccd from `dir` if a library matching the requested linking is present
+fn link_from(dir: &Path, requested: Option<Linking>, rpath: bool) -> bool {
+    let has_static = dir.join("libqhyccd.a").exists();
+    let dynamic = dynamic_library(dir);
+    let linking = match requested {
+        Some(Linking::Static) if has_static => Linking::Static,
+        Some(Linking::Dynamic) if dynamic.is_some() => Linking::Dynamic,
+        None if has_static => Linking::Static,
+        None if dynamic.is_some() => Linking::Dynamic,
+        _ => return false,
+    };
+    println!("cargo:rustc-link-search=native={}", dir.display());
+    if linking == Linking::Dynamic {
+        let versioned = dynamic.filter(|library| {
+            !library.ends_with("libqhyccd.so") && !library.ends_with("libqhyccd.dylib")
+        });
+        if let Some(library) = versioned {
+            link_versioned(&library);
+        }
+        if rpath {
+            println!("cargo:rustc-link-arg=-Wl,-rpath,{}", dir.display());
         }
-        false => {
-            println!("cargo:rustc-link-search=native=/usr/local/lib");
+    }
+    emit_link(linking);
+    true
+}
+
+/// The shared library in `dir`, either `libqhyccd.so`, `libqhyccd.dylib` or, if the SDK only
+/// installed its versioned names, the shortest `libqhyccd.so.*` which is usually the soname
+fn dynamic_library(dir: &Path) -> Option<PathBuf> {
+    for name in ["libqhyccd.so", "libqhyccd.dylib"] {
+        if dir.join(name).exists() {
+            return Some(dir.join(name));
         }
     }
-    println!("cargo:rustc-link-lib=static=qhyccd");
-    println!("cargo:rustc-link-lib=dylib=usb-1.0");
-    println!("cargo:rustc-link-lib=dylib=stdc++");
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            // `exists` follows symlinks, so a dangling soname link is skipped
+            name.starts_with("libqhyccd.so.") && path.exists()
+        })
+        .min_by_key(|path| path.as_os_str().len())
+}
+
+/// `-lqhyccd` only finds `libqhyccd.so`, link it to `library` in `$OUT_DIR` and search there
+fn link_versioned(library: &Path) {
+    let dir = Path::new(&env::var("OUT_DIR").unwrap()).join("qhyccd");
+    let link = dir.join("libqhyccd.so");
+    fs::create_dir_all(&dir).expect("creating the library link directory failed");
+    let _ = fs::remove_file(&link);
+    #[cfg(unix)]
+    let linked = std::os::unix::fs::symlink(library, &link);
+    #[cfg(not(unix))]
+    let linked = fs::copy(library, &link).map(|_| ());
+    linked.unwrap_or_else(|error| {
+        panic!(
+            "linking {} to {} failed: {}",
+            link.display(),
+            library.display(),
+            error
+        )
+    });
+    println!("cargo:rustc-link-search=native={}", dir.display());
+}
+
+fn emit_link(linking: Linking) {
+    match linking {
+        Linking::Static => {
+            println!("cargo:rustc-link-lib=static=qhyccd");
+            println!("cargo:rustc-link-lib=dylib=usb-1.0");
+            println!("cargo:rustc-link-lib=dylib=stdc++");
+        }
+        Linking::Dynamic => println!("cargo:rustc-link-lib=dylib=qhyccd"),
+    }
+}
+
+/// Generate the SDK functions, constants and `CONTROL_ID` enum from qhyccd.h into
+/// `$OUT_DIR/generated.rs`
+#[cfg(feature = "bindgen")]
+fn generate_bindings() {
+    println!("cargo:rerun-if-env-changed=QHYCCD_INCLUDE_DIR");
+    let include = env::var("QHYCCD_INCLUDE_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| {
+            Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("qhyccd-sdk/include")
+        });
+    let header = include.join("qhyccd.h");
+    println!("cargo:rerun-if-changed={}", header.display());
+    bindgen::Builder::default()
+        .header(header.to_string_lossy())
+        .clang_arg(format!("-I{}", include.display()))
+        .clang_arg("-xc++")
+        .disable_header_comment()
+        .allowlist_function(".*QHYCCD.*")
+        .allowlist_type("CONTROL_ID")
+        .allowlist_var("QHYCCD_.*")
+        .generate()
+        .unwrap_or_else(|error| panic!("generating bindings from {:?} failed: {}", header, error))
+        .write_to_file(Path::new(&env::var("OUT_DIR").unwrap()).join("generated.rs"))
+        .expect("writing generated bindings failed");
 }
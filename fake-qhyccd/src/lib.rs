@@ -0,0 +1,725 @@
+//! Simulated libqhyccd for testing libqhyccd-sys without a camera
+//!
+//! The library exports the SDK functions declared in libqhyccd-sys' bindings.rs and backs them
+//! with simulated cameras. The cameras are configured through environment variables read by
+//! `InitQHYCCDResource`:
+//!
+//! - `FAKE_QHYCCD_CAMERAS` number of connected cameras, defaults to 1
+//! - `FAKE_QHYCCD_MODEL` model name, defaults to `QHY178M`
+//! - `FAKE_QHYCCD_WIDTH` / `FAKE_QHYCCD_HEIGHT` sensor size including overscan, defaults to
+//!   656x480
+//! - `FAKE_QHYCCD_OVERSCAN` width of the overscan strip on the left, defaults to 16
+//! - `FAKE_QHYCCD_SDK_VERSION` reported SDK version, defaults to 23.9.6.14
+//!
+//! Frames contain a deterministic gradient that changes with every frame.
+#![allow(
+    non_snake_case,
+    non_upper_case_globals,
+    clippy::missing_safety_doc,
+    clippy::too_many_arguments
+)]
+
+use std::{
+    collections::HashMap,
+    env,
+    ffi::{c_char, c_void, CStr},
+    sync::Mutex,
+};
+
+#[allow(dead_code, non_camel_case_types, non_upper_case_globals)]
+#[path = "../../src/generated.rs"]
+mod generated;
+
+use generated::*;
+
+pub type QhyccdHandle = *const c_void;
+
+/// value, minimum, maximum and step of the supported parameters
+const PARAMETERS: [(u32, f64, f64, f64, f64); 13] = [
+    (CONTROL_ID_CONTROL_WBR, 128.0, 0.0, 255.0, 1.0),
+    (CONTROL_ID_CONTROL_WBB, 128.0, 0.0, 255.0, 1.0),
+    (CONTROL_ID_CONTROL_WBG, 128.0, 0.0, 255.0, 1.0),
+    (CONTROL_ID_CONTROL_GAIN, 0.0, 0.0, 100.0, 1.0),
+    (CONTROL_ID_CONTROL_OFFSET, 30.0, 0.0, 255.0, 1.0),
+    (
+        CONTROL_ID_CONTROL_EXPOSURE,
+        2000.0,
+        1.0,
+        3_600_000_000.0,
+        1.0,
+    ),
+    (CONTROL_ID_CONTROL_TRANSFERBIT, 16.0, 8.0, 16.0, 8.0),
+    (CONTROL_ID_CONTROL_USBTRAFFIC, 30.0, 0.0, 255.0, 1.0),
+    (CONTROL_ID_CONTROL_CURTEMP, 20.0, -50.0, 50.0, 0.1),
+    (CONTROL_ID_CONTROL_DDR, 0.0, 0.0, 1.0, 1.0),
+    (CONTROL_ID_DDR_BUFFER_CAPACITY, 0.0, 0.0, 2048.0, 1.0),
+    (
+        CONTROL_ID_hasHardwareFrameCounter,
+        0.0,
+        0.0,
+        u32::MAX as f64,
+        1.0,
+    ),
+    (CONTROL_ID_CAM_CurveSystemGain, 0.0, 0.0, 0.0, 0.0),
+];
+
+const FEATURES: [u32; 11] = [
+    CONTROL_ID_CAM_BIN1X1MODE,
+    CONTROL_ID_CAM_BIN2X2MODE,
+    CONTROL_ID_CAM_BIN4X4MODE,
+    CONTROL_ID_CAM_8BITS,
+    CONTROL_ID_CAM_16BITS,
+    CONTROL_ID_CAM_SINGLEFRAMEMODE,
+    CONTROL_ID_CAM_LIVEVIDEOMODE,
+    CONTROL_ID_hasHardwareFrameCounter,
+    CONTROL_ID_CAM_CurveSystemGain,
+    CONTROL_ID_CAM_CurveFullWell,
+    CONTROL_ID_CAM_CurveReadoutNoise,
+];
+
+const READ_MODES: [&str; 2] = ["STANDARD MODE", "HIGH GAIN MODE"];
+
+#[derive(Debug, Clone)]
+struct Config {
+    cameras: u32,
+    model: String,
+    width: u32,
+    height: u32,
+    overscan: u32,
+    sdk_version: [u32; 4],
+}
+
+impl Config {
+    fn from_env() -> Self {
+        let number = |name: &str, default: u32| {
+            env::var(name)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default)
+        };
+        let sdk_version = env::var("FAKE_QHYCCD_SDK_VERSION")
+            .ok()
+            .and_then(|version| {
+                let parts = version
+                    .split('.')
+                    .map(|part| part.parse().ok())
+                    .collect::<Option<Vec<u32>>>()?;
+                <[u32; 4]>::try_from(parts).ok()
+            })
+            .unwrap_or([23, 9, 6, 14]);
+        Self {
+            cameras: number("FAKE_QHYCCD_CAMERAS", 1),
+            model: env::var("FAKE_QHYCCD_MODEL").unwrap_or_else(|_| "QHY178M".to_string()),
+            width: number("FAKE_QHYCCD_WIDTH", 656),
+            height: number("FAKE_QHYCCD_HEIGHT", 480),
+            overscan: number("FAKE_QHYCCD_OVERSCAN", 16),
+            sdk_version,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Camera {
+    id: String,
+    open: bool,
+    initialized: bool,
+    live: bool,
+    exposing: bool,
+    read_mode: u32,
+    stream_mode: u8,
+    bits: u32,
+    bin: u32,
+    roi: [u32; 4],
+    frame: u32,
+    parameters: HashMap<u32, f64>,
+}
+
+#[derive(Debug)]
+struct State {
+    config: Config,
+    cameras: Vec<Camera>,
+}
+
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+fn with_state<T>(default: T, f: impl FnOnce(&mut State) -> T) -> T {
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    match state.as_mut() {
+        Some(state) => f(state),
+        None => default,
+    }
+}
+
+unsafe fn write<T>(ptr: *mut T, value: T) {
+    if !ptr.is_null() {
+        *ptr = value;
+    }
+}
+
+unsafe fn write_str(ptr: *mut c_char, value: &str) {
+    if ptr.is_null() {
+        return;
+    }
+    for (i, byte) in value.bytes().enumerate() {
+        *ptr.add(i) = byte as c_char;
+    }
+    *ptr.add(value.len()) = 0;
+}
+
+fn status(ok: bool) -> u32 {
+    match ok {
+        true => QHYCCD_SUCCESS,
+        false => QHYCCD_ERROR,
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn InitQHYCCDResource() -> u32 {
+    let config = Config::from_env();
+    let cameras = (0..config.cameras)
+        .map(|index| Camera {
+            id: format!("{}-fake{:04}", config.model, index),
+            open: false,
+            initialized: false,
+            live: false,
+            exposing: false,
+            read_mode: 0,
+            stream_mode: 0,
+            bits: 16,
+            bin: 1,
+            roi: [0, 0, config.width, config.height],
+            frame: 0,
+            parameters: PARAMETERS
+                .iter()
+                .map(|(id, value, ..)| (*id, *value))
+                .collect(),
+        })
+        .collect();
+    *STATE.lock().unwrap_or_else(|e| e.into_inner()) = Some(State { config, cameras });
+    QHYCCD_SUCCESS
+}
+
+#[no_mangle]
+pub extern "C" fn ReleaseQHYCCDResource() -> u32 {
+    *STATE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    QHYCCD_SUCCESS
+}
+
+#[no_mangle]
+pub extern "C" fn ScanQHYCCD() -> u32 {
+    with_state(QHYCCD_ERROR, |state| state.cameras.len() as u32)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDSDKVersion(
+    year: *mut u32,
+    month: *mut u32,
+    day: *mut u32,
+    subday: *mut u32,
+) -> u32 {
+    let [y, m, d, s] = Config::from_env().sdk_version;
+    write(year, y);
+    write(month, m);
+    write(day, d);
+    write(subday, s);
+    QHYCCD_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDId(index: u32, id: *mut c_char) -> u32 {
+    with_state(QHYCCD_ERROR, |state| {
+        match state.cameras.get(index as usize) {
+            Some(camera) => {
+                write_str(id, &camera.id);
+                QHYCCD_SUCCESS
+            }
+            None => QHYCCD_ERROR,
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn OpenQHYCCD(id: *const c_char) -> QhyccdHandle {
+    if id.is_null() {
+        return std::ptr::null();
+    }
+    let id = CStr::from_ptr(id).to_string_lossy();
+    with_state(std::ptr::null(), |state| {
+        match state.cameras.iter_mut().position(|camera| camera.id == id) {
+            Some(index) => {
+                state.cameras[index].open = true;
+                (index + 1) as QhyccdHandle
+            }
+            None => std::ptr::null(),
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn CloseQHYCCD(handle: QhyccdHandle) -> u32 {
+    camera(handle, |_, camera| {
+        camera.open = false;
+        camera.live = false;
+        true
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn InitQHYCCD(handle: QhyccdHandle) -> u32 {
+    camera(handle, |_, camera| {
+        camera.initialized = true;
+        true
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDFWVersion(handle: QhyccdHandle, buf: *mut u8) -> u32 {
+    camera(handle, |_, _| {
+        // 2023-09-06 in the packed format of the SDK
+        write(buf, 0x79);
+        write(buf.add(1), 6);
+        true
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDModel(handle: QhyccdHandle, model: *mut c_char) -> u32 {
+    camera(handle, |config, _| {
+        write_str(model, &config.model);
+        true
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn GetQHYCCDType(handle: QhyccdHandle) -> u32 {
+    with_state(QHYCCD_ERROR, |state| {
+        match state.cameras.get((handle as usize).wrapping_sub(1)) {
+            Some(camera) if camera.open => 4010,
+            _ => QHYCCD_ERROR,
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn IsQHYCCDControlAvailable(handle: QhyccdHandle, controlId: u32) -> u32 {
+    camera(handle, |_, _| {
+        FEATURES.contains(&controlId) || PARAMETERS.iter().any(|(id, ..)| *id == controlId)
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn SetQHYCCDReadMode(handle: QhyccdHandle, mode: u32) -> u32 {
+    camera(handle, |_, camera| {
+        let valid = (mode as usize) < READ_MODES.len();
+        if valid {
+            camera.read_mode = mode;
+        }
+        valid
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDReadMode(handle: QhyccdHandle, mode: *mut u32) -> u32 {
+    camera(handle, |_, camera| {
+        write(mode, camera.read_mode);
+        true
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDNumberOfReadModes(
+    handle: QhyccdHandle,
+    num_modes: *mut u32,
+) -> u32 {
+    camera(handle, |_, _| {
+        write(num_modes, READ_MODES.len() as u32);
+        true
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDReadModeName(
+    handle: QhyccdHandle,
+    mode: u32,
+    name: *mut c_char,
+) -> u32 {
+    camera(handle, |_, _| match READ_MODES.get(mode as usize) {
+        Some(mode) => {
+            write_str(name, mode);
+            true
+        }
+        None => false,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDReadModeResolution(
+    handle: QhyccdHandle,
+    mode: u32,
+    width: *mut u32,
+    height: *mut u32,
+) -> u32 {
+    camera(handle, |config, _| {
+        write(width, config.width);
+        write(height, config.height);
+        (mode as usize) < READ_MODES.len()
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn SetQHYCCDStreamMode(handle: QhyccdHandle, mode: u8) -> u32 {
+    camera(handle, |_, camera| {
+        camera.stream_mode = mode;
+        mode <= 1
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDChipInfo(
+    handle: QhyccdHandle,
+    chipw: *mut f64,
+    chiph: *mut f64,
+    imagew: *mut u32,
+    imageh: *mut u32,
+    pixelw: *mut f64,
+    pixelh: *mut f64,
+    bpp: *mut u32,
+) -> u32 {
+    camera(handle, |config, camera| {
+        write(chipw, config.width as f64 * 0.0024);
+        write(chiph, config.height as f64 * 0.0024);
+        write(imagew, config.width);
+        write(imageh, config.height);
+        write(pixelw, 2.4);
+        write(pixelh, 2.4);
+        write(bpp, camera.bits);
+        true
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDOverScanArea(
+    handle: QhyccdHandle,
+    startx: *mut u32,
+    starty: *mut u32,
+    sizex: *mut u32,
+    sizey: *mut u32,
+) -> u32 {
+    camera(handle, |config, _| {
+        write(startx, 0);
+        write(starty, 0);
+        write(sizex, config.overscan);
+        write(sizey, config.height);
+        true
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDEffectiveArea(
+    handle: QhyccdHandle,
+    startx: *mut u32,
+    starty: *mut u32,
+    sizex: *mut u32,
+    sizey: *mut u32,
+) -> u32 {
+    camera(handle, |config, _| {
+        write(startx, config.overscan);
+        write(starty, 0);
+        write(sizex, config.width - config.overscan);
+        write(sizey, config.height);
+        true
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn SetQHYCCDBitsMode(handle: QhyccdHandle, bits: u32) -> u32 {
+    camera(handle, |_, camera| {
+        let valid = bits == 8 || bits == 16;
+        if valid {
+            camera.bits = bits;
+        }
+        valid
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn SetQHYCCDDebayerOnOff(handle: QhyccdHandle, _onoff: bool) -> u32 {
+    camera(handle, |_, _| true)
+}
+
+#[no_mangle]
+pub extern "C" fn SetQHYCCDBinMode(handle: QhyccdHandle, wbin: u32, hbin: u32) -> u32 {
+    camera(handle, |_, camera| {
+        let valid = wbin == hbin && [1, 2, 4].contains(&wbin);
+        if valid {
+            camera.bin = wbin;
+        }
+        valid
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn SetQHYCCDResolution(
+    handle: QhyccdHandle,
+    x: u32,
+    y: u32,
+    xsize: u32,
+    ysize: u32,
+) -> u32 {
+    camera(handle, |config, camera| {
+        // unbinned end of the ROI, `None` if it does not fit in a u32
+        let end = |start: u32, size: u32| start.checked_add(size)?.checked_mul(camera.bin);
+        let fits = matches!(end(x, xsize), Some(right) if right <= config.width)
+            && matches!(end(y, ysize), Some(bottom) if bottom <= config.height)
+            && xsize > 0
+            && ysize > 0;
+        if fits {
+            camera.roi = [x, y, xsize, ysize];
+        }
+        fits
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDCurrentROI(
+    handle: QhyccdHandle,
+    startx: *mut u32,
+    starty: *mut u32,
+    sizex: *mut u32,
+    sizey: *mut u32,
+) -> u32 {
+    camera(handle, |_, camera| {
+        let [x, y, w, h] = camera.roi;
+        write(startx, x);
+        write(starty, y);
+        write(sizex, w);
+        write(sizey, h);
+        true
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn SetQHYCCDParam(handle: QhyccdHandle, controlId: u32, value: f64) -> u32 {
+    camera(handle, |_, camera| match range(controlId) {
+        Some((min, max, _)) if value >= min && value <= max => {
+            camera.parameters.insert(controlId, value);
+            true
+        }
+        _ => false,
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn GetQHYCCDParam(handle: QhyccdHandle, controlId: u32) -> f64 {
+    with_state(QHYCCD_ERROR as f64, |state| {
+        let camera = match state.cameras.get((handle as usize).wrapping_sub(1)) {
+            Some(camera) if camera.open => camera,
+            _ => return QHYCCD_ERROR as f64,
+        };
+        let gain = camera.parameters[&CONTROL_ID_CONTROL_GAIN];
+        match controlId {
+            CONTROL_ID_hasHardwareFrameCounter => camera.frame as f64,
+            CONTROL_ID_CAM_CurveSystemGain => 4.0 / (1.0 + gain / 10.0),
+            CONTROL_ID_CAM_CurveFullWell => 15000.0 / (1.0 + gain / 10.0),
+            CONTROL_ID_CAM_CurveReadoutNoise => 1.0 + 2.0 / (1.0 + gain / 10.0),
+            id => camera
+                .parameters
+                .get(&id)
+                .copied()
+                .unwrap_or(QHYCCD_ERROR as f64),
+        }
+    })
+}
+
+fn range(control_id: u32) -> Option<(f64, f64, f64)> {
+    PARAMETERS
+        .iter()
+        .find(|(id, ..)| *id == control_id)
+        .map(|(_, _, min, max, step)| (*min, *max, *step))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDParamMinMaxStep(
+    handle: QhyccdHandle,
+    controlId: u32,
+    min: *mut f64,
+    max: *mut f64,
+    step: *mut f64,
+) -> u32 {
+    camera(handle, |_, _| match range(controlId) {
+        Some((lower, upper, increment)) => {
+            write(min, lower);
+            write(max, upper);
+            write(step, increment);
+            true
+        }
+        None => false,
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn GetQHYCCDMemLength(handle: QhyccdHandle) -> u32 {
+    with_state(QHYCCD_ERROR, |state| {
+        match state.cameras.get((handle as usize).wrapping_sub(1)) {
+            Some(camera) if camera.open => state.config.width * state.config.height * 2,
+            _ => QHYCCD_ERROR,
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn ExpQHYCCDSingleFrame(handle: QhyccdHandle) -> u32 {
+    camera(handle, |_, camera| {
+        camera.exposing = camera.initialized && camera.stream_mode == 0;
+        camera.exposing
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDSingleFrame(
+    handle: QhyccdHandle,
+    w: *mut u32,
+    h: *mut u32,
+    bpp: *mut u32,
+    channels: *mut u32,
+    imgdata: *mut u8,
+) -> u32 {
+    camera(handle, |_, camera| {
+        if !camera.exposing {
+            return false;
+        }
+        camera.exposing = false;
+        read_frame(camera, w, h, bpp, channels, imgdata);
+        true
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn BeginQHYCCDLive(handle: QhyccdHandle) -> u32 {
+    camera(handle, |_, camera| {
+        camera.live = camera.initialized && camera.stream_mode == 1;
+        camera.live
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDLiveFrame(
+    handle: QhyccdHandle,
+    w: *mut u32,
+    h: *mut u32,
+    bpp: *mut u32,
+    channels: *mut u32,
+    imgdata: *mut u8,
+) -> u32 {
+    camera(handle, |_, camera| {
+        if !camera.live {
+            return false;
+        }
+        read_frame(camera, w, h, bpp, channels, imgdata);
+        true
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn StopQHYCCDLive(handle: QhyccdHandle) -> u32 {
+    camera(handle, |_, camera| {
+        camera.live = false;
+        true
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn GetQHYCCDExposureRemaining(handle: QhyccdHandle) -> u32 {
+    camera(handle, |_, _| true)
+}
+
+#[no_mangle]
+pub extern "C" fn CancelQHYCCDExposing(handle: QhyccdHandle) -> u32 {
+    camera(handle, |_, camera| {
+        camera.exposing = false;
+        true
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn CancelQHYCCDExposingAndReadout(handle: QhyccdHandle) -> u32 {
+    CancelQHYCCDExposing(handle)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn GetQHYCCDHumidity(handle: QhyccdHandle, hd: *mut f64) -> u32 {
+    camera(handle, |_, _| {
+        write(hd, 20.0);
+        true
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn QHYCCDSensorPhaseReTrain(handle: QhyccdHandle) -> u32 {
+    camera(handle, |_, _| true)
+}
+
+#[no_mangle]
+pub extern "C" fn GetQHYCCDSensorPhaseReTrain(handle: QhyccdHandle) -> u32 {
+    camera(handle, |_, _| true)
+}
+
+#[no_mangle]
+pub extern "C" fn SetQHYCCDEnableLiveModeAntiRBI(handle: QhyccdHandle, _value: u32) -> u32 {
+    camera(handle, |_, _| true)
+}
+
+#[no_mangle]
+pub extern "C" fn SetQHYCCDTwoChannelCombineParameter(
+    handle: QhyccdHandle,
+    _x: f64,
+    _ah: f64,
+    _bh: f64,
+    _al: f64,
+    _bl: f64,
+) -> u32 {
+    camera(handle, |_, _| true)
+}
+
+/// Run `f` on the open camera behind `handle` and map its result to an SDK status code
+fn camera(handle: QhyccdHandle, f: impl FnOnce(&Config, &mut Camera) -> bool) -> u32 {
+    with_state(QHYCCD_ERROR, |state| {
+        let config = &state.config;
+        match state.cameras.get_mut((handle as usize).wrapping_sub(1)) {
+            Some(camera) if camera.open => status(f(config, camera)),
+            _ => QHYCCD_ERROR,
+        }
+    })
+}
+
+/// Fill `imgdata` with a gradient over the current sub frame
+unsafe fn read_frame(
+    camera: &mut Camera,
+    w: *mut u32,
+    h: *mut u32,
+    bpp: *mut u32,
+    channels: *mut u32,
+    imgdata: *mut u8,
+) {
+    camera.frame = camera.frame.wrapping_add(1);
+    let [start_x, start_y, width, height] = camera.roi;
+    let bytes = (camera.bits / 8) as usize;
+    for y in 0..height {
+        for x in 0..width {
+            let value = ((start_x + x + start_y + y) * 16 + camera.frame) as u16;
+            let offset = (y * width + x) as usize * bytes;
+            match bytes {
+                1 => *imgdata.add(offset) = (value >> 8) as u8,
+                _ => {
+                    let [low, high] = value.to_le_bytes();
+                    *imgdata.add(offset) = low;
+                    *imgdata.add(offset + 1) = high;
+                }
+            }
+        }
+    }
+    write(w, width);
+    write(h, height);
+    write(bpp, camera.bits);
+    write(channels, 1);
+}
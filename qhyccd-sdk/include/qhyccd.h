@@ -0,0 +1,176 @@
+/*
+ * Declarations of the QHYCCD SDK 23.9.6.14 used by libqhyccd-sys, collected from the SDK's
+ * qhyccd.h, qhyccdstruct.h and qhyccderr.h. Like the SDK headers this is C++.
+ *
+ * build.rs runs bindgen on this header with the `bindgen` feature, the output is checked in as
+ * src/generated.rs. Point QHYCCD_INCLUDE_DIR at the include directory of an installed SDK to
+ * generate the bindings from its headers instead.
+ */
+#ifndef QHYCCD_H
+#define QHYCCD_H
+
+#include <stdint.h>
+
+#define EXPORTC extern "C"
+
+#if defined(_WIN32)
+#define STDCALL __stdcall
+#else
+#define STDCALL
+#endif
+
+#define QHYCCD_PCIE 9
+#define QHYCCD_WINPCAP 8
+#define QHYCCD_QGIGAE 7
+#define QHYCCD_USBSYNC 6
+#define QHYCCD_USBASYNC 5
+#define QHYCCD_COLOR 4
+#define QHYCCD_MONO 3
+#define QHYCCD_COOL 2
+#define QHYCCD_NOTCOOL 1
+#define QHYCCD_SUCCESS 0
+#define QHYCCD_ERROR 0xFFFFFFFF
+
+typedef void qhyccd_handle;
+
+enum CONTROL_ID {
+    CONTROL_BRIGHTNESS,
+    CONTROL_CONTRAST,
+    CONTROL_WBR,
+    CONTROL_WBB,
+    CONTROL_WBG,
+    CONTROL_GAMMA,
+    CONTROL_GAIN,
+    CONTROL_OFFSET,
+    CONTROL_EXPOSURE,
+    CONTROL_SPEED,
+    CONTROL_TRANSFERBIT,
+    CONTROL_CHANNELS,
+    CONTROL_USBTRAFFIC,
+    CONTROL_ROWNOISERE,
+    CONTROL_CURTEMP,
+    CONTROL_CURPWM,
+    CONTROL_MANULPWM,
+    CONTROL_CFWPORT,
+    CONTROL_COOLER,
+    CONTROL_ST4PORT,
+    CAM_COLOR,
+    CAM_BIN1X1MODE,
+    CAM_BIN2X2MODE,
+    CAM_BIN3X3MODE,
+    CAM_BIN4X4MODE,
+    CAM_MECHANICALSHUTTER,
+    CAM_TRIGER_INTERFACE,
+    CAM_TECOVERPROTECT_INTERFACE,
+    CAM_SINGNALCLAMP_INTERFACE,
+    CAM_FINETONE_INTERFACE,
+    CAM_SHUTTERMOTORHEATING_INTERFACE,
+    CAM_CALIBRATEFPN_INTERFACE,
+    CAM_CHIPTEMPERATURESENSOR_INTERFACE,
+    CAM_USBREADOUTSLOWEST_INTERFACE,
+    CAM_8BITS,
+    CAM_16BITS,
+    CAM_GPS,
+    CAM_IGNOREOVERSCAN_INTERFACE,
+    QHYCCD_3A_AUTOBALANCE,
+    QHYCCD_3A_AUTOEXPOSURE,
+    QHYCCD_3A_AUTOFOCUS,
+    CONTROL_AMPV,
+    CONTROL_VCAM,
+    CAM_VIEW_MODE,
+    CONTROL_CFWSLOTSNUM,
+    IS_EXPOSING_DONE,
+    ScreenStretchB,
+    ScreenStretchW,
+    CONTROL_DDR,
+    CAM_LIGHT_PERFORMANCE_MODE,
+    CAM_QHY5II_GUIDE_MODE,
+    DDR_BUFFER_CAPACITY,
+    DDR_BUFFER_READ_THRESHOLD,
+    DefaultGain,
+    DefaultOffset,
+    OutputDataActualBits,
+    OutputDataAlignment,
+    CAM_SINGLEFRAMEMODE,
+    CAM_LIVEVIDEOMODE,
+    CAM_IS_COLOR,
+    hasHardwareFrameCounter,
+    CONTROL_MAX_ID_Error,
+    CAM_HUMIDITY,
+    CAM_PRESSURE,
+    CONTROL_VACUUM_PUMP,
+    CONTROL_SensorChamberCycle_PUMP,
+    CAM_32BITS,
+    CAM_Sensor_ULVO_Status,
+    CAM_SensorPhaseReTrain,
+    CAM_InitConfigFromFlash,
+    CAM_TRIGER_MODE,
+    CAM_TRIGER_OUT,
+    CAM_BURST_MODE,
+    CAM_SPEAKER_LED_ALARM,
+    CAM_WATCH_DOG_FPGA,
+    CAM_BIN6X6MODE,
+    CAM_BIN8X8MODE,
+    CAM_GlobalSensorGPSLED,
+    CONTROL_ImgProc,
+    CONTROL_RemoveRBI,
+    CONTROL_GlobalReset,
+    CONTROL_FrameDetect,
+    CAM_GainDBConversion,
+    CAM_CurveSystemGain,
+    CAM_CurveFullWell,
+    CAM_CurveReadoutNoise,
+    CONTROL_MAX_ID,
+    CONTROL_AUTOWHITEBALANCE = 1024,
+    CONTROL_AUTOEXPOSURE,
+    CONTROL_AUTOEXPmessureValue,
+    CONTROL_AUTOEXPmessureMethod,
+    CONTROL_ImageStabilization,
+    CONTROL_GAINdB
+};
+
+EXPORTC uint32_t STDCALL InitQHYCCDResource(void);
+EXPORTC uint32_t STDCALL ScanQHYCCD(void);
+EXPORTC uint32_t STDCALL GetQHYCCDSDKVersion(uint32_t *year, uint32_t *month, uint32_t *day, uint32_t *subday);
+EXPORTC uint32_t STDCALL GetQHYCCDId(uint32_t index, char *id);
+EXPORTC qhyccd_handle *STDCALL OpenQHYCCD(const char *id);
+EXPORTC uint32_t STDCALL GetQHYCCDFWVersion(qhyccd_handle *handle, uint8_t *buf);
+EXPORTC uint32_t STDCALL IsQHYCCDControlAvailable(qhyccd_handle *handle, CONTROL_ID controlId);
+EXPORTC uint32_t STDCALL SetQHYCCDReadMode(qhyccd_handle *handle, uint32_t mode);
+EXPORTC uint32_t STDCALL SetQHYCCDStreamMode(qhyccd_handle *handle, uint8_t mode);
+EXPORTC uint32_t STDCALL InitQHYCCD(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL GetQHYCCDChipInfo(qhyccd_handle *handle, double *chipw, double *chiph, uint32_t *imagew, uint32_t *imageh, double *pixelw, double *pixelh, uint32_t *bpp);
+EXPORTC uint32_t STDCALL SetQHYCCDBitsMode(qhyccd_handle *handle, uint32_t bits);
+EXPORTC uint32_t STDCALL SetQHYCCDDebayerOnOff(qhyccd_handle *handle, bool onoff);
+EXPORTC uint32_t STDCALL SetQHYCCDBinMode(qhyccd_handle *handle, uint32_t wbin, uint32_t hbin);
+EXPORTC uint32_t STDCALL SetQHYCCDResolution(qhyccd_handle *handle, uint32_t x, uint32_t y, uint32_t xsize, uint32_t ysize);
+EXPORTC uint32_t STDCALL SetQHYCCDParam(qhyccd_handle *handle, CONTROL_ID controlId, double value);
+EXPORTC double STDCALL GetQHYCCDParam(qhyccd_handle *handle, CONTROL_ID controlId);
+EXPORTC uint32_t STDCALL GetQHYCCDParamMinMaxStep(qhyccd_handle *handle, CONTROL_ID controlId, double *min, double *max, double *step);
+EXPORTC uint32_t STDCALL BeginQHYCCDLive(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL GetQHYCCDMemLength(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL GetQHYCCDLiveFrame(qhyccd_handle *handle, uint32_t *w, uint32_t *h, uint32_t *bpp, uint32_t *channels, uint8_t *imgdata);
+EXPORTC uint32_t STDCALL StopQHYCCDLive(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL CloseQHYCCD(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL ReleaseQHYCCDResource(void);
+EXPORTC uint32_t STDCALL GetQHYCCDOverScanArea(qhyccd_handle *handle, uint32_t *startx, uint32_t *starty, uint32_t *sizex, uint32_t *sizey);
+EXPORTC uint32_t STDCALL GetQHYCCDEffectiveArea(qhyccd_handle *handle, uint32_t *startx, uint32_t *starty, uint32_t *sizex, uint32_t *sizey);
+EXPORTC uint32_t STDCALL ExpQHYCCDSingleFrame(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL GetQHYCCDSingleFrame(qhyccd_handle *handle, uint32_t *w, uint32_t *h, uint32_t *bpp, uint32_t *channels, uint8_t *imgdata);
+EXPORTC uint32_t STDCALL GetQHYCCDNumberOfReadModes(qhyccd_handle *handle, uint32_t *num_modes);
+EXPORTC uint32_t STDCALL GetQHYCCDReadModeResolution(qhyccd_handle *handle, uint32_t mode, uint32_t *width, uint32_t *height);
+EXPORTC uint32_t STDCALL GetQHYCCDReadModeName(qhyccd_handle *handle, uint32_t mode, char *name);
+EXPORTC uint32_t STDCALL GetQHYCCDReadMode(qhyccd_handle *handle, uint32_t *mode);
+EXPORTC uint32_t STDCALL GetQHYCCDModel(qhyccd_handle *handle, char *model);
+EXPORTC uint32_t STDCALL GetQHYCCDType(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL GetQHYCCDExposureRemaining(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL CancelQHYCCDExposing(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL CancelQHYCCDExposingAndReadout(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL GetQHYCCDCurrentROI(qhyccd_handle *handle, uint32_t *startx, uint32_t *starty, uint32_t *sizex, uint32_t *sizey);
+EXPORTC uint32_t STDCALL GetQHYCCDHumidity(qhyccd_handle *handle, double *hd);
+EXPORTC uint32_t STDCALL QHYCCDSensorPhaseReTrain(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL GetQHYCCDSensorPhaseReTrain(qhyccd_handle *handle);
+EXPORTC uint32_t STDCALL SetQHYCCDEnableLiveModeAntiRBI(qhyccd_handle *handle, uint32_t value);
+EXPORTC uint32_t STDCALL SetQHYCCDTwoChannelCombineParameter(qhyccd_handle *handle, double x, double ah, double bh, double al, double bl);
+
+#endif
@@ -0,0 +1,480 @@
+//! The functions of lib.rs call the SDK through a `CameraBackend`, by default `SdkBackend`
+//! which forwards to libqhyccd. Tests swap in `MockBackend` (with the `testing` feature) or their
+//! own implementation with `set_backend`.
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::locking::Serialized;
+use crate::{
+    bindings, CCDChipArea, CCDChipInfo, CameraFeature, HdrCombine, ImageData, QhyccdHandle,
+    SdkFunction,
+};
+
+/// One method per SDK function, named after the function in snake case
+///
+/// Methods return the SDK status code or value unchanged and write their results to the `&mut`
+/// arguments like the C functions do, so an implementation decides exactly what the SDK would
+/// have answered.
+pub trait CameraBackend: Send + Sync {
+    /// Make sure the SDK can be called, the error is the reason it cannot
+    fn load(&self) -> Result<(), String> {
+        Ok(())
+    }
+    /// Whether the SDK exports `function`
+    fn is_available(&self, _function: SdkFunction) -> bool {
+        true
+    }
+    fn init_qhyccd_resource(&self) -> u32;
+    fn release_qhyccd_resource(&self) -> u32;
+    fn scan_qhyccd(&self) -> u32;
+    fn get_qhyccd_sdk_version(
+        &self,
+        year: &mut u32,
+        month: &mut u32,
+        day: &mut u32,
+        subday: &mut u32,
+    ) -> u32;
+    /// Writes the nul terminated id to `id`
+    fn get_qhyccd_id(&self, index: u32, id: &mut [u8]) -> u32;
+    /// Returns a null handle if the camera cannot be opened
+    fn open_qhyccd(&self, id: &str) -> QhyccdHandle;
+    fn close_qhyccd(&self, handle: QhyccdHandle) -> u32;
+    fn init_qhyccd(&self, handle: QhyccdHandle) -> u32;
+    fn get_qhyccd_fw_version(&self, handle: QhyccdHandle, version: &mut [u8]) -> u32;
+    /// Writes the nul terminated model to `model`
+    fn get_qhyccd_model(&self, handle: QhyccdHandle, model: &mut [u8]) -> u32;
+    fn get_qhyccd_type(&self, handle: QhyccdHandle) -> u32;
+    fn is_qhyccd_control_available(&self, handle: QhyccdHandle, feature: CameraFeature) -> u32;
+    fn set_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: u32) -> u32;
+    fn get_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: &mut u32) -> u32;
+    fn get_qhyccd_number_of_read_modes(&self, handle: QhyccdHandle, modes: &mut u32) -> u32;
+    /// Writes the nul terminated name to `name`
+    fn get_qhyccd_read_mode_name(&self, handle: QhyccdHandle, mode: u32, name: &mut [u8]) -> u32;
+    fn get_qhyccd_read_mode_resolution(
+        &self,
+        handle: QhyccdHandle,
+        mode: u32,
+        width: &mut u32,
+        height: &mut u32,
+    ) -> u32;
+    fn set_qhyccd_stream_mode(&self, handle: QhyccdHandle, mode: u8) -> u32;
+    fn get_qhyccd_chip_info(&self, handle: QhyccdHandle, info: &mut CCDChipInfo) -> u32;
+    fn get_qhyccd_overscan_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32;
+    fn get_qhyccd_effective_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32;
+    fn set_qhyccd_bits_mode(&self, handle: QhyccdHandle, bits: u32) -> u32;
+    fn set_qhyccd_debayer_on_off(&self, handle: QhyccdHandle, on: bool) -> u32;
+    fn set_qhyccd_bin_mode(&self, handle: QhyccdHandle, bin_x: u32, bin_y: u32) -> u32;
+    fn set_qhyccd_resolution(&self, handle: QhyccdHandle, roi: CCDChipArea) -> u32;
+    fn get_qhyccd_current_roi(&self, handle: QhyccdHandle, roi: &mut CCDChipArea) -> u32;
+    fn set_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature, value: f64) -> u32;
+    fn get_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature) -> f64;
+    fn get_qhyccd_param_min_max_step(
+        &self,
+        handle: QhyccdHandle,
+        feature: CameraFeature,
+        min: &mut f64,
+        max: &mut f64,
+        step: &mut f64,
+    ) -> u32;
+    fn get_qhyccd_mem_length(&self, handle: QhyccdHandle) -> u32;
+    fn exp_qhyccd_single_frame(&self, handle: QhyccdHandle) -> u32;
+    /// `image.data` is the buffer to read into, the other fields are set from the frame
+    fn get_qhyccd_single_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32;
+    fn begin_qhyccd_live(&self, handle: QhyccdHandle) -> u32;
+    /// `image.data` is the buffer to read into, the other fields are set from the frame
+    fn get_qhyccd_live_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32;
+    fn stop_qhyccd_live(&self, handle: QhyccdHandle) -> u32;
+    fn get_qhyccd_exposure_remaining(&self, handle: QhyccdHandle) -> u32;
+    fn cancel_qhyccd_exposing(&self, handle: QhyccdHandle) -> u32;
+    fn cancel_qhyccd_exposing_and_readout(&self, handle: QhyccdHandle) -> u32;
+    fn get_qhyccd_humidity(&self, handle: QhyccdHandle, humidity: &mut f64) -> u32;
+    fn qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32;
+    fn get_qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32;
+    fn set_qhyccd_enable_live_mode_anti_rbi(&self, handle: QhyccdHandle, value: u32) -> u32;
+    fn set_qhyccd_two_channel_combine_parameter(
+        &self,
+        handle: QhyccdHandle,
+        combine: HdrCombine,
+    ) -> u32;
+}
+
+/// Calls the QHYCCD SDK
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SdkBackend;
+
+/// Copies `bytes` to `buffer` as a nul terminated C string, truncating it to fit
+pub(crate) fn write_c_string(buffer: &mut [u8], bytes: &[u8]) {
+    let len = bytes.len().min(buffer.len().saturating_sub(1));
+    buffer[..len].copy_from_slice(&bytes[..len]);
+    if let Some(end) = buffer.get_mut(len) {
+        *end = 0;
+    }
+}
+
+/// The bytes of `buffer` before the first nul
+pub(crate) fn c_string_bytes(buffer: &[u8]) -> &[u8] {
+    let len = buffer
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(buffer.len());
+    &buffer[..len]
+}
+
+impl CameraBackend for SdkBackend {
+    fn load(&self) -> Result<(), String> {
+        bindings::load()
+    }
+
+    fn is_available(&self, function: SdkFunction) -> bool {
+        bindings::is_available(function)
+    }
+
+    fn init_qhyccd_resource(&self) -> u32 {
+        unsafe { bindings::InitQHYCCDResource() }
+    }
+
+    fn release_qhyccd_resource(&self) -> u32 {
+        unsafe { bindings::ReleaseQHYCCDResource() }
+    }
+
+    fn scan_qhyccd(&self) -> u32 {
+        unsafe { bindings::ScanQHYCCD() }
+    }
+
+    fn get_qhyccd_sdk_version(
+        &self,
+        year: &mut u32,
+        month: &mut u32,
+        day: &mut u32,
+        subday: &mut u32,
+    ) -> u32 {
+        unsafe { bindings::GetQHYCCDSDKVersion(year, month, day, subday) }
+    }
+
+    fn get_qhyccd_id(&self, index: u32, id: &mut [u8]) -> u32 {
+        // the SDK writes up to 32 bytes
+        let mut buffer = [0u8; 32];
+        let result = unsafe { bindings::GetQHYCCDId(index, buffer.as_mut_ptr().cast()) };
+        write_c_string(id, c_string_bytes(&buffer));
+        result
+    }
+
+    fn open_qhyccd(&self, id: &str) -> QhyccdHandle {
+        match std::ffi::CString::new(id) {
+            Ok(id) => QhyccdHandle::new(unsafe { bindings::OpenQHYCCD(id.as_ptr()) }),
+            Err(_) => QhyccdHandle::new(std::ptr::null_mut()),
+        }
+    }
+
+    fn close_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::CloseQHYCCD(handle.ptr) }
+    }
+
+    fn init_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::InitQHYCCD(handle.ptr) }
+    }
+
+    fn get_qhyccd_fw_version(&self, handle: QhyccdHandle, version: &mut [u8]) -> u32 {
+        // the SDK writes up to 32 bytes
+        let mut buffer = [0u8; 32];
+        let result = unsafe { bindings::GetQHYCCDFWVersion(handle.ptr, buffer.as_mut_ptr()) };
+        let len = version.len().min(buffer.len());
+        version[..len].copy_from_slice(&buffer[..len]);
+        result
+    }
+
+    fn get_qhyccd_model(&self, handle: QhyccdHandle, model: &mut [u8]) -> u32 {
+        // the SDK writes up to 80 bytes
+        let mut buffer = [0u8; 80];
+        let result = unsafe { bindings::GetQHYCCDModel(handle.ptr, buffer.as_mut_ptr().cast()) };
+        write_c_string(model, c_string_bytes(&buffer));
+        result
+    }
+
+    fn get_qhyccd_type(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::GetQHYCCDType(handle.ptr) }
+    }
+
+    fn is_qhyccd_control_available(&self, handle: QhyccdHandle, feature: CameraFeature) -> u32 {
+        unsafe { bindings::IsQHYCCDControlAvailable(handle.ptr, feature as u32) }
+    }
+
+    fn set_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: u32) -> u32 {
+        unsafe { bindings::SetQHYCCDReadMode(handle.ptr, mode) }
+    }
+
+    fn get_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: &mut u32) -> u32 {
+        unsafe { bindings::GetQHYCCDReadMode(handle.ptr, mode) }
+    }
+
+    fn get_qhyccd_number_of_read_modes(&self, handle: QhyccdHandle, modes: &mut u32) -> u32 {
+        unsafe { bindings::GetQHYCCDNumberOfReadModes(handle.ptr, modes) }
+    }
+
+    fn get_qhyccd_read_mode_name(&self, handle: QhyccdHandle, mode: u32, name: &mut [u8]) -> u32 {
+        // the SDK writes up to 80 bytes
+        let mut buffer = [0u8; 80];
+        let result = unsafe {
+            bindings::GetQHYCCDReadModeName(handle.ptr, mode, buffer.as_mut_ptr().cast())
+        };
+        write_c_string(name, c_string_bytes(&buffer));
+        result
+    }
+
+    fn get_qhyccd_read_mode_resolution(
+        &self,
+        handle: QhyccdHandle,
+        mode: u32,
+        width: &mut u32,
+        height: &mut u32,
+    ) -> u32 {
+        unsafe { bindings::GetQHYCCDReadModeResolution(handle.ptr, mode, width, height) }
+    }
+
+    fn set_qhyccd_stream_mode(&self, handle: QhyccdHandle, mode: u8) -> u32 {
+        unsafe { bindings::SetQHYCCDStreamMode(handle.ptr, mode) }
+    }
+
+    fn get_qhyccd_chip_info(&self, handle: QhyccdHandle, info: &mut CCDChipInfo) -> u32 {
+        unsafe {
+            bindings::GetQHYCCDChipInfo(
+                handle.ptr,
+                &mut info.chip_width,
+                &mut info.chip_height,
+                &mut info.image_width,
+                &mut info.image_height,
+                &mut info.pixel_width,
+                &mut info.pixel_height,
+                &mut info.bits_per_pixel,
+            )
+        }
+    }
+
+    fn get_qhyccd_overscan_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        unsafe {
+            bindings::GetQHYCCDOverScanArea(
+                handle.ptr,
+                &mut area.start_x,
+                &mut area.start_y,
+                &mut area.width,
+                &mut area.height,
+            )
+        }
+    }
+
+    fn get_qhyccd_effective_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        unsafe {
+            bindings::GetQHYCCDEffectiveArea(
+                handle.ptr,
+                &mut area.start_x,
+                &mut area.start_y,
+                &mut area.width,
+                &mut area.height,
+            )
+        }
+    }
+
+    fn set_qhyccd_bits_mode(&self, handle: QhyccdHandle, bits: u32) -> u32 {
+        unsafe { bindings::SetQHYCCDBitsMode(handle.ptr, bits) }
+    }
+
+    fn set_qhyccd_debayer_on_off(&self, handle: QhyccdHandle, on: bool) -> u32 {
+        unsafe { bindings::SetQHYCCDDebayerOnOff(handle.ptr, on) }
+    }
+
+    fn set_qhyccd_bin_mode(&self, handle: QhyccdHandle, bin_x: u32, bin_y: u32) -> u32 {
+        unsafe { bindings::SetQHYCCDBinMode(handle.ptr, bin_x, bin_y) }
+    }
+
+    fn set_qhyccd_resolution(&self, handle: QhyccdHandle, roi: CCDChipArea) -> u32 {
+        unsafe {
+            bindings::SetQHYCCDResolution(
+                handle.ptr,
+                roi.start_x,
+                roi.start_y,
+                roi.width,
+                roi.height,
+            )
+        }
+    }
+
+    fn get_qhyccd_current_roi(&self, handle: QhyccdHandle, roi: &mut CCDChipArea) -> u32 {
+        unsafe {
+            bindings::GetQHYCCDCurrentROI(
+                handle.ptr,
+                &mut roi.start_x,
+                &mut roi.start_y,
+                &mut roi.width,
+                &mut roi.height,
+            )
+        }
+    }
+
+    fn set_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature, value: f64) -> u32 {
+        unsafe { bindings::SetQHYCCDParam(handle.ptr, feature as u32, value) }
+    }
+
+    fn get_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature) -> f64 {
+        unsafe { bindings::GetQHYCCDParam(handle.ptr, feature as u32) }
+    }
+
+    fn get_qhyccd_param_min_max_step(
+        &self,
+        handle: QhyccdHandle,
+        feature: CameraFeature,
+        min: &mut f64,
+        max: &mut f64,
+        step: &mut f64,
+    ) -> u32 {
+        unsafe { bindings::GetQHYCCDParamMinMaxStep(handle.ptr, feature as u32, min, max, step) }
+    }
+
+    fn get_qhyccd_mem_length(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::GetQHYCCDMemLength(handle.ptr) }
+    }
+
+    fn exp_qhyccd_single_frame(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::ExpQHYCCDSingleFrame(handle.ptr) }
+    }
+
+    fn get_qhyccd_single_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        unsafe {
+            bindings::GetQHYCCDSingleFrame(
+                handle.ptr,
+                &mut image.width,
+                &mut image.height,
+                &mut image.bits_per_pixel,
+                &mut image.channels,
+                image.data.as_mut_ptr(),
+            )
+        }
+    }
+
+    fn begin_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::BeginQHYCCDLive(handle.ptr) }
+    }
+
+    fn get_qhyccd_live_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        unsafe {
+            bindings::GetQHYCCDLiveFrame(
+                handle.ptr,
+                &mut image.width,
+                &mut image.height,
+                &mut image.bits_per_pixel,
+                &mut image.channels,
+                image.data.as_mut_ptr(),
+            )
+        }
+    }
+
+    fn stop_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::StopQHYCCDLive(handle.ptr) }
+    }
+
+    fn get_qhyccd_exposure_remaining(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::GetQHYCCDExposureRemaining(handle.ptr) }
+    }
+
+    fn cancel_qhyccd_exposing(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::CancelQHYCCDExposing(handle.ptr) }
+    }
+
+    fn cancel_qhyccd_exposing_and_readout(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::CancelQHYCCDExposingAndReadout(handle.ptr) }
+    }
+
+    fn get_qhyccd_humidity(&self, handle: QhyccdHandle, humidity: &mut f64) -> u32 {
+        unsafe { bindings::GetQHYCCDHumidity(handle.ptr, humidity) }
+    }
+
+    fn qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::QHYCCDSensorPhaseReTrain(handle.ptr) }
+    }
+
+    fn get_qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        unsafe { bindings::GetQHYCCDSensorPhaseReTrain(handle.ptr) }
+    }
+
+    fn set_qhyccd_enable_live_mode_anti_rbi(&self, handle: QhyccdHandle, value: u32) -> u32 {
+        unsafe { bindings::SetQHYCCDEnableLiveModeAntiRBI(handle.ptr, value) }
+    }
+
+    fn set_qhyccd_two_channel_combine_parameter(
+        &self,
+        handle: QhyccdHandle,
+        combine: HdrCombine,
+    ) -> u32 {
+        unsafe {
+            bindings::SetQHYCCDTwoChannelCombineParameter(
+                handle.ptr,
+                combine.threshold,
+                combine.high_gain_scale,
+                combine.high_gain_offset,
+                combine.low_gain_scale,
+                combine.low_gain_offset,
+            )
+        }
+    }
+}
+
+/// Backend used by all functions of the crate, `None` means `SdkBackend`
+static BACKEND: Mutex<Option<Arc<dyn CameraBackend>>> = Mutex::new(None);
+
+/// The installed backend behind the locks of `Serialized`
+pub(crate) fn backend() -> Serialized {
+    Serialized::new(
+        BACKEND
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_or_insert_with(|| Arc::new(SdkBackend))
+            .clone(),
+    )
+}
+
+/// Serializes the holders of `BackendGuard`
+static INSTALLED: Mutex<()> = Mutex::new(());
+
+/// Keeps the backend installed by `set_backend`, dropping it goes back to calling the QHYCCD SDK
+#[must_use = "the backend is removed again when the guard is dropped"]
+pub struct BackendGuard {
+    _installed: MutexGuard<'static, ()>,
+}
+
+impl Drop for BackendGuard {
+    fn drop(&mut self) {
+        install(None);
+    }
+}
+
+fn install(backend: Option<Arc<dyn CameraBackend>>) {
+    *BACKEND.lock().unwrap_or_else(|e| e.into_inner()) = backend;
+    crate::reset_cached_state();
+}
+
+/// Route all SDK calls of the process through `backend` until the returned guard is dropped
+///
+/// The backend is process wide, so `set_backend` waits until the guard of the previous backend
+/// is dropped. Tests installing their own backends therefore run one at a time and a failing
+/// test does not leave its backend behind. Dropping a guard must not wait on another thread
+/// that is calling `set_backend`. Cached SDK state such as the SDK version and the current
+/// binning is reset. Calls reach the backend serialized as described for `QhyccdHandle`.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "testing")] {
+/// # use libqhyccd_sys::{get_camera_id, init_sdk, scan_qhyccd, set_backend, MockBackend};
+/// let mock = MockBackend::new();
+/// let _backend = set_backend(mock.clone());
+///
+/// init_sdk().unwrap();
+/// assert_eq!(scan_qhyccd().unwrap(), 1);
+/// assert_eq!(mock.calls(), ["GetQHYCCDSDKVersion", "InitQHYCCDResource", "ScanQHYCCD"]);
+/// # }
+/// ```
+pub fn set_backend(backend: impl CameraBackend + 'static) -> BackendGuard {
+    let installed = INSTALLED.lock().unwrap_or_else(|e| e.into_inner());
+    install(Some(Arc::new(backend)));
+    BackendGuard {
+        _installed: installed,
+    }
+}
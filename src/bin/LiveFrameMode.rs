@@ -2,11 +2,11 @@
 use std::{thread, time::Duration};
 
 use libqhyccd_sys::{
-    begin_live, close_camera, end_live, get_camera_id, get_ccd_info, get_effective_area,
-    get_firmware_version, get_image_size, get_live_frame, get_overscan_area, get_sdk_version,
-    init_camera, init_sdk, is_feature_supported, open_camera, release_sdk, scan_qhyccd,
-    set_bin_mode, set_bit_mode, set_parameter, set_readout_mode, set_roi, set_stream_mode,
-    CameraFeature, CameraStreamMode,
+    begin_live, close_camera, end_live, get_camera_id, get_ccd_info, get_ddr_buffer,
+    get_effective_area, get_firmware_version, get_image_size, get_live_frame, get_overscan_area,
+    get_sdk_version, init_camera, init_sdk, is_feature_supported, open_camera, release_sdk,
+    scan_qhyccd, set_bin_mode, set_bit_depth, set_ddr_enabled, set_exposure, set_parameter,
+    set_readout_mode, set_roi, set_stream_mode, BitDepth, CameraFeature, CameraStreamMode,
 };
 use tracing::trace;
 use tracing_subscriber::FmtSubscriber;
@@ -52,22 +52,22 @@ fn main() {
     let effective_area = get_effective_area(camera).expect("get_camera_effective_area failed");
     trace!(effective_area = ?effective_area);
 
-    set_bit_mode(camera, 8).expect("set_camera_bit_mode failed");
+    set_bit_depth(camera, BitDepth::Eight).expect("set_bit_depth failed");
+    trace!(bit_depth = ?BitDepth::Eight);
     set_bin_mode(camera, 1, 1).expect("set_camera_bin_mode failed");
 
     set_roi(camera, effective_area).expect("set_camera_roi failed");
     trace!(roi = ?effective_area);
-    set_parameter(camera, CameraFeature::ControlTransferBit, 8.0)
-        .expect("set_camera_parameter failed");
-    trace!(control_transferbit = 8.0);
-    set_parameter(camera, CameraFeature::ControlExposure, 2000.0)
-        .expect("set_camera_parameter failed");
-    trace!(control_exposure = 2000.0);
+    set_exposure(camera, Duration::from_millis(2)).expect("set_exposure failed");
+    trace!(exposure = ?Duration::from_millis(2));
     set_parameter(camera, CameraFeature::ControlUsbTraffic, 255.0)
         .expect("set_camera_parameter failed");
     trace!(control_usb_traffic = 255.0);
-    set_parameter(camera, CameraFeature::ControlDDR, 1.0).expect("set_camera_parameter failed");
-    trace!(control_ddr = 1.0);
+    if is_feature_supported(camera, CameraFeature::ControlDDR).is_ok() {
+        set_ddr_enabled(camera, true).expect("set_ddr_enabled failed");
+        let ddr_buffer = get_ddr_buffer(camera).expect("get_ddr_buffer failed");
+        trace!(ddr_buffer = ?ddr_buffer);
+    }
     begin_live(camera).expect("begin_camera_live failed");
     let size = get_image_size(camera).expect("get_camera_image_size failed");
     trace!(image_size = ?size);
@@ -1,10 +1,12 @@
 #![allow(non_snake_case)]
+use std::time::Duration;
+
 use libqhyccd_sys::{
     close_camera, get_camera_id, get_ccd_info, get_effective_area, get_firmware_version,
     get_image_size, get_overscan_area, get_sdk_version, get_single_frame, init_camera, init_sdk,
-    is_feature_supported, open_camera, release_sdk, scan_qhyccd, set_bin_mode, set_bit_mode,
-    set_parameter, set_readout_mode, set_roi, set_stream_mode, start_single_frame_exposure,
-    CameraFeature, CameraStreamMode,
+    is_feature_supported, open_camera, release_sdk, scan_qhyccd, set_bin_mode, set_bit_depth,
+    set_exposure, set_parameter, set_readout_mode, set_roi, set_stream_mode,
+    start_single_frame_exposure, BitDepth, CameraFeature, CameraStreamMode,
 };
 use tracing::{error, trace};
 use tracing_subscriber::FmtSubscriber;
@@ -94,9 +96,8 @@ fn main() {
         }
     }
 
-    set_parameter(camera, CameraFeature::ControlExposure, 2000.0)
-        .expect("setting exposure time failed");
-    trace!(exposure_time = 2000.0);
+    set_exposure(camera, Duration::from_millis(2)).expect("setting exposure time failed");
+    trace!(exposure_time = ?Duration::from_millis(2));
 
     set_roi(camera, effective_area).expect("set_camera_roi failed");
     trace!(roi = ?effective_area);
@@ -104,16 +105,8 @@ fn main() {
     set_bin_mode(camera, 1, 1).expect("set_camera_bin_mode failed");
     trace!(bin_mode = "(1, 1)");
 
-    match is_feature_supported(camera, CameraFeature::ControlTransferBit) {
-        Ok(_) => {
-            trace!(cam_transfer_bit = 16.0);
-            set_bit_mode(camera, 16).expect("setting transfer bits to 16 failed");
-        }
-        Err(_) => {
-            error!("setting transfer bits is not supported");
-            return;
-        }
-    }
+    set_bit_depth(camera, BitDepth::Sixteen).expect("set_bit_depth failed");
+    trace!(bit_depth = ?BitDepth::Sixteen);
 
     trace!("beginning single frame capture");
     start_single_frame_exposure(camera).expect("start_camera_single_frame_exposure failed");
@@ -1,7 +1,7 @@
 use libqhyccd_sys::{
-    close_camera, get_camera_id, get_number_of_readout_modes, get_readout_mode,
-    get_readout_mode_name, get_readout_mode_resolution, get_sdk_version, init_camera, init_sdk,
-    open_camera, release_sdk, scan_qhyccd, set_readout_mode, set_stream_mode, CameraStreamMode,
+    close_camera, get_camera_id, get_readout_mode, get_sdk_version, init_camera, init_sdk,
+    open_camera, readout_modes, release_sdk, scan_qhyccd, set_readout_mode, set_stream_mode,
+    CameraStreamMode,
 };
 use tracing::trace;
 use tracing_subscriber::FmtSubscriber;
@@ -35,13 +35,11 @@ fn main() {
 
     init_camera(camera).expect("init_camera failed");
 
-    let number_of_readout_modes = get_number_of_readout_modes(camera).unwrap();
-    trace!(number_of_readout_modes = ?number_of_readout_modes);
-    for i in 0..number_of_readout_modes {
-        let name = get_readout_mode_name(camera, i).unwrap();
-        println!("{}: {}", i, name);
-        let resolution = get_readout_mode_resolution(camera, i).unwrap();
-        println!("{}: {}, {}", i, resolution.0, resolution.1);
+    for mode in readout_modes(camera).expect("readout_modes failed") {
+        println!(
+            "{}: {}, {}, {}",
+            mode.id, mode.name, mode.width, mode.height
+        );
     }
 
     let read_out_mode = get_readout_mode(camera).expect("get_readout_mode failed");
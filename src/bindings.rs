@@ -1,101 +1,458 @@
 use std::ffi::c_char;
 
-pub const QHYCCD_PCIE: u32 = 9;
-pub const QHYCCD_WINPCAP: u32 = 8;
-pub const QHYCCD_QGIGAE: u32 = 7;
-pub const QHYCCD_USBSYNC: u32 = 6;
-pub const QHYCCD_USBASYNC: u32 = 5;
-pub const QHYCCD_COLOR: u32 = 4;
-pub const QHYCCD_MONO: u32 = 3;
-pub const QHYCCD_COOL: u32 = 2;
-pub const QHYCCD_NOTCOO: u32 = 1;
-pub const QHYCCD_SUCCESS: u32 = 0;
-pub const QHYCCD_ERROR: u32 = 0xFFFFFFFF;
-
-pub type QhyccdHandle = *const std::ffi::c_void;
-
-#[link(name = "qhyccd", kind = "static")]
-extern "C" {
-
-    pub fn InitQHYCCDResource() -> u32;
-    pub fn ScanQHYCCD() -> u32;
-    pub fn GetQHYCCDSDKVersion(
-        year: *mut u32,
-        month: *mut u32,
-        day: *mut u32,
-        subday: *mut u32,
-    ) -> u32;
-    pub fn GetQHYCCDId(index: u32, id: *mut c_char) -> u32;
-    pub fn OpenQHYCCD(id: *const c_char) -> QhyccdHandle;
-    pub fn GetQHYCCDFWVersion(h: QhyccdHandle, buf: *mut u8) -> u32;
-    pub fn IsQHYCCDControlAvailable(h: QhyccdHandle, controlId: u32) -> u32;
-    pub fn SetQHYCCDReadMode(h: QhyccdHandle, mode: u32) -> u32;
-    pub fn SetQHYCCDStreamMode(h: QhyccdHandle, mode: u8) -> u32;
-    pub fn InitQHYCCD(h: QhyccdHandle) -> u32;
-    pub fn GetQHYCCDChipInfo(
-        handle: QhyccdHandle,
-        chipw: *mut f64,
-        chiph: *mut f64,
-        imagew: *mut u32,
-        imageh: *mut u32,
-        pixelw: *mut f64,
-        pixelh: *mut f64,
-        bpp: *mut u32,
-    ) -> u32;
-    pub fn SetQHYCCDBitsMode(handle: QhyccdHandle, bits: u32) -> u32;
-    pub fn SetQHYCCDDebayerOnOff(handle: QhyccdHandle, onoff: bool) -> u32;
-    pub fn SetQHYCCDBinMode(handle: QhyccdHandle, wbin: u32, hbin: u32) -> u32;
-    pub fn SetQHYCCDResolution(handle: QhyccdHandle, x: u32, y: u32, xsize: u32, ysize: u32)
-        -> u32;
-    pub fn SetQHYCCDParam(handle: QhyccdHandle, controlId: u32, value: f64) -> u32;
-    pub fn BeginQHYCCDLive(handle: QhyccdHandle) -> u32;
-    pub fn GetQHYCCDMemLength(handle: QhyccdHandle) -> u32;
-    pub fn GetQHYCCDLiveFrame(
-        handle: QhyccdHandle,
-        w: *mut u32,
-        h: *mut u32,
-        bpp: *mut u32,
-        channels: *mut u32,
-        imgdata: *mut u8,
-    ) -> u32;
-    pub fn StopQHYCCDLive(handle: QhyccdHandle) -> u32;
-    pub fn CloseQHYCCD(handle: QhyccdHandle) -> u32;
-    pub fn ReleaseQHYCCDResource() -> u32;
-    pub fn GetQHYCCDOverScanArea(
-        handle: QhyccdHandle,
-        startx: *mut u32,
-        starty: *mut u32,
-        sizex: *mut u32,
-        sizey: *mut u32,
-    ) -> u32;
-    pub fn GetQHYCCDEffectiveArea(
-        handle: QhyccdHandle,
-        startx: *mut u32,
-        starty: *mut u32,
-        sizex: *mut u32,
-        sizey: *mut u32,
-    ) -> u32;
-    pub fn ExpQHYCCDSingleFrame(handle: QhyccdHandle) -> u32;
-    pub fn GetQHYCCDSingleFrame(
-        handle: QhyccdHandle,
-        w: *mut u32,
-        h: *mut u32,
-        bpp: *mut u32,
-        channels: *mut u32,
-        imgdata: *mut u8,
-    ) -> u32;
-    pub fn GetQHYCCDNumberOfReadModes(handle: QhyccdHandle, num_modes: *mut u32) -> u32;
-    pub fn GetQHYCCDReadModeResolution(
-        handle: QhyccdHandle,
-        mode: u32,
-        width: *mut u32,
-        height: *mut u32,
-    ) -> u32;
-    pub fn GetQHYCCDReadModeName(handle: QhyccdHandle, mode: u32, name: *mut c_char) -> u32;
-    pub fn GetQHYCCDReadMode(handle: QhyccdHandle, mode: *mut u32) -> u32;
-    pub fn GetQHYCCDModel(handle: QhyccdHandle, model: *mut c_char) -> u32;
-    pub fn GetQHYCCDType(handle: QhyccdHandle) -> u32;
-    pub fn GetQHYCCDExposureRemaining(handle: QhyccdHandle) -> u32;
-    pub fn CancelQHYCCDExposing(handle: QhyccdHandle) -> u32;
-    pub fn CancelQHYCCDExposingAndReadout(handle: QhyccdHandle) -> u32;
+/// Output of bindgen for qhyccd-sdk/include/qhyccd.h, see `generate_bindings` in build.rs.
+/// Regenerate with `cargo build --features bindgen,runtime-link` and copy
+/// `$OUT_DIR/generated.rs` to src/generated.rs, CI checks that the two do not differ.
+#[allow(
+    dead_code,
+    non_camel_case_types,
+    non_snake_case,
+    non_upper_case_globals
+)]
+mod generated {
+    #[cfg(not(feature = "bindgen"))]
+    include!("generated.rs");
+    #[cfg(feature = "bindgen")]
+    include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+}
+
+pub use generated::*;
+
+pub type QhyccdHandle = *mut qhyccd_handle;
+
+/// Declares how the SDK functions are called: linked at build time through the declarations
+/// bindgen generated, or, with the `runtime-link` feature, loaded from libqhyccd when first used.
+/// The signatures listed here are checked against the generated declarations at compile time.
+///
+/// Functions in the `optional` section are missing from older SDK builds. With `runtime-link`
+/// calling a missing function returns the SDK error value instead of failing to load.
+macro_rules! qhyccd_functions {
+    (
+        required {
+            $(pub fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty;)*
+        }
+        optional {
+            $(pub fn $opt_name:ident($($opt_arg:ident: $opt_ty:ty),* $(,)?) -> $opt_ret:ty;)*
+        }
+    ) => {
+        // evaluated at compile time only, so the generated functions are checked but not linked
+        const _: () = {
+            $(let _: unsafe extern "C" fn($($ty),*) -> $ret = generated::$name;)*
+            $(let _: unsafe extern "C" fn($($opt_ty),*) -> $opt_ret = generated::$opt_name;)*
+        };
+
+        #[cfg(feature = "runtime-link")]
+        #[allow(non_snake_case)]
+        struct Api {
+            _library: libloading::Library,
+            $($name: unsafe extern "C" fn($($ty),*) -> $ret,)*
+            $($opt_name: Option<unsafe extern "C" fn($($opt_ty),*) -> $opt_ret>,)*
+        }
+
+        #[cfg(feature = "runtime-link")]
+        impl Api {
+            #[allow(non_snake_case)]
+            unsafe fn new(library: libloading::Library) -> Result<Api, String> {
+                $(
+                    let $name = *library
+                        .get::<unsafe extern "C" fn($($ty),*) -> $ret>(
+                            concat!(stringify!($name), "\0").as_bytes(),
+                        )
+                        .map_err(|error| {
+                            format!("{} not found in libqhyccd: {}", stringify!($name), error)
+                        })?;
+                )*
+                $(
+                    let $opt_name = library
+                        .get::<unsafe extern "C" fn($($opt_ty),*) -> $opt_ret>(
+                            concat!(stringify!($opt_name), "\0").as_bytes(),
+                        )
+                        .ok()
+                        .map(|symbol| *symbol);
+                    if $opt_name.is_none() {
+                        tracing::warn!(missing_function = stringify!($opt_name));
+                    }
+                )*
+                Ok(Api {
+                    _library: library,
+                    $($name,)*
+                    $($opt_name,)*
+                })
+            }
+        }
+
+        /// An SDK function, named like in qhyccd.h
+        #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+        pub enum SdkFunction {
+            $($name,)*
+            $($opt_name,)*
+        }
+
+        impl SdkFunction {
+            /// Name of the function in qhyccd.h
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(SdkFunction::$name => stringify!($name),)*
+                    $(SdkFunction::$opt_name => stringify!($opt_name),)*
+                }
+            }
+        }
+
+        /// Whether `function` can be called, always true unless runtime linked
+        #[cfg(not(feature = "runtime-link"))]
+        pub fn is_available(_function: SdkFunction) -> bool {
+            true
+        }
+
+        /// Whether `function` can be called, false if the SDK or the function could not be loaded
+        #[cfg(feature = "runtime-link")]
+        pub fn is_available(function: SdkFunction) -> bool {
+            match api() {
+                Ok(api) => match function {
+                    $(SdkFunction::$name => true,)*
+                    $(SdkFunction::$opt_name => api.$opt_name.is_some(),)*
+                },
+                Err(_) => false,
+            }
+        }
+
+        $(
+            #[cfg(feature = "runtime-link")]
+            #[allow(non_snake_case, clippy::too_many_arguments)]
+            pub unsafe fn $name($($arg: $ty),*) -> $ret {
+                match api() {
+                    Ok(api) => (api.$name)($($arg),*),
+                    Err(_) => Missing::missing(),
+                }
+            }
+        )*
+        $(
+            #[cfg(feature = "runtime-link")]
+            #[allow(non_snake_case, clippy::too_many_arguments)]
+            pub unsafe fn $opt_name($($opt_arg: $opt_ty),*) -> $opt_ret {
+                match api().ok().and_then(|api| api.$opt_name) {
+                    Some(function) => function($($opt_arg),*),
+                    None => Missing::missing(),
+                }
+            }
+        )*
+    };
+}
+
+qhyccd_functions! {
+    required {
+        pub fn InitQHYCCDResource() -> u32;
+        pub fn ScanQHYCCD() -> u32;
+        pub fn GetQHYCCDSDKVersion(
+            year: *mut u32,
+            month: *mut u32,
+            day: *mut u32,
+            subday: *mut u32,
+        ) -> u32;
+        pub fn GetQHYCCDId(index: u32, id: *mut c_char) -> u32;
+        pub fn OpenQHYCCD(id: *const c_char) -> QhyccdHandle;
+        pub fn GetQHYCCDFWVersion(handle: QhyccdHandle, buf: *mut u8) -> u32;
+        pub fn IsQHYCCDControlAvailable(handle: QhyccdHandle, controlId: u32) -> u32;
+        pub fn SetQHYCCDReadMode(handle: QhyccdHandle, mode: u32) -> u32;
+        pub fn SetQHYCCDStreamMode(handle: QhyccdHandle, mode: u8) -> u32;
+        pub fn InitQHYCCD(handle: QhyccdHandle) -> u32;
+        pub fn GetQHYCCDChipInfo(
+            handle: QhyccdHandle,
+            chipw: *mut f64,
+            chiph: *mut f64,
+            imagew: *mut u32,
+            imageh: *mut u32,
+            pixelw: *mut f64,
+            pixelh: *mut f64,
+            bpp: *mut u32,
+        ) -> u32;
+        pub fn SetQHYCCDBitsMode(handle: QhyccdHandle, bits: u32) -> u32;
+        pub fn SetQHYCCDDebayerOnOff(handle: QhyccdHandle, onoff: bool) -> u32;
+        pub fn SetQHYCCDBinMode(handle: QhyccdHandle, wbin: u32, hbin: u32) -> u32;
+        pub fn SetQHYCCDResolution(handle: QhyccdHandle, x: u32, y: u32, xsize: u32, ysize: u32)
+            -> u32;
+        pub fn SetQHYCCDParam(handle: QhyccdHandle, controlId: u32, value: f64) -> u32;
+        pub fn GetQHYCCDParam(handle: QhyccdHandle, controlId: u32) -> f64;
+        pub fn GetQHYCCDParamMinMaxStep(
+            handle: QhyccdHandle,
+            controlId: u32,
+            min: *mut f64,
+            max: *mut f64,
+            step: *mut f64,
+        ) -> u32;
+        pub fn BeginQHYCCDLive(handle: QhyccdHandle) -> u32;
+        pub fn GetQHYCCDMemLength(handle: QhyccdHandle) -> u32;
+        pub fn GetQHYCCDLiveFrame(
+            handle: QhyccdHandle,
+            w: *mut u32,
+            h: *mut u32,
+            bpp: *mut u32,
+            channels: *mut u32,
+            imgdata: *mut u8,
+        ) -> u32;
+        pub fn StopQHYCCDLive(handle: QhyccdHandle) -> u32;
+        pub fn CloseQHYCCD(handle: QhyccdHandle) -> u32;
+        pub fn ReleaseQHYCCDResource() -> u32;
+        pub fn GetQHYCCDOverScanArea(
+            handle: QhyccdHandle,
+            startx: *mut u32,
+            starty: *mut u32,
+            sizex: *mut u32,
+            sizey: *mut u32,
+        ) -> u32;
+        pub fn GetQHYCCDEffectiveArea(
+            handle: QhyccdHandle,
+            startx: *mut u32,
+            starty: *mut u32,
+            sizex: *mut u32,
+            sizey: *mut u32,
+        ) -> u32;
+        pub fn ExpQHYCCDSingleFrame(handle: QhyccdHandle) -> u32;
+        pub fn GetQHYCCDSingleFrame(
+            handle: QhyccdHandle,
+            w: *mut u32,
+            h: *mut u32,
+            bpp: *mut u32,
+            channels: *mut u32,
+            imgdata: *mut u8,
+        ) -> u32;
+        pub fn GetQHYCCDNumberOfReadModes(handle: QhyccdHandle, num_modes: *mut u32) -> u32;
+        pub fn GetQHYCCDReadModeResolution(
+            handle: QhyccdHandle,
+            mode: u32,
+            width: *mut u32,
+            height: *mut u32,
+        ) -> u32;
+        pub fn GetQHYCCDReadModeName(handle: QhyccdHandle, mode: u32, name: *mut c_char) -> u32;
+        pub fn GetQHYCCDReadMode(handle: QhyccdHandle, mode: *mut u32) -> u32;
+        pub fn GetQHYCCDModel(handle: QhyccdHandle, model: *mut c_char) -> u32;
+        pub fn GetQHYCCDType(handle: QhyccdHandle) -> u32;
+        pub fn GetQHYCCDExposureRemaining(handle: QhyccdHandle) -> u32;
+        pub fn CancelQHYCCDExposing(handle: QhyccdHandle) -> u32;
+        pub fn CancelQHYCCDExposingAndReadout(handle: QhyccdHandle) -> u32;
+    }
+    optional {
+        pub fn GetQHYCCDCurrentROI(
+            handle: QhyccdHandle,
+            startx: *mut u32,
+            starty: *mut u32,
+            sizex: *mut u32,
+            sizey: *mut u32,
+        ) -> u32;
+        pub fn GetQHYCCDHumidity(handle: QhyccdHandle, hd: *mut f64) -> u32;
+        pub fn QHYCCDSensorPhaseReTrain(handle: QhyccdHandle) -> u32;
+        pub fn GetQHYCCDSensorPhaseReTrain(handle: QhyccdHandle) -> u32;
+        pub fn SetQHYCCDEnableLiveModeAntiRBI(handle: QhyccdHandle, value: u32) -> u32;
+        pub fn SetQHYCCDTwoChannelCombineParameter(
+            handle: QhyccdHandle,
+            x: f64,
+            ah: f64,
+            bh: f64,
+            al: f64,
+            bl: f64,
+        ) -> u32;
+    }
+}
+
+/// Load the SDK, a no-op unless the `runtime-link` feature is enabled
+#[cfg(not(feature = "runtime-link"))]
+pub fn load() -> Result<(), String> {
+    Ok(())
+}
+
+/// Load libqhyccd from `QHYCCD_LIBRARY` or the default library search path
+#[cfg(feature = "runtime-link")]
+pub fn load() -> Result<(), String> {
+    api().map(|_| ())
+}
+
+/// The loaded SDK, only set once loading succeeded so a failed load is retried on the next call
+#[cfg(feature = "runtime-link")]
+static API: std::sync::Mutex<Option<&'static Api>> = std::sync::Mutex::new(None);
+
+#[cfg(feature = "runtime-link")]
+fn api() -> Result<&'static Api, String> {
+    load_api(|| {
+        std::env::var_os("QHYCCD_LIBRARY").unwrap_or_else(|| libloading::library_filename("qhyccd"))
+    })
+}
+
+/// The cached SDK, or the SDK loaded from `path` if none is cached yet
+#[cfg(feature = "runtime-link")]
+fn load_api(path: impl FnOnce() -> std::ffi::OsString) -> Result<&'static Api, String> {
+    let mut cached = API.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(api) = *cached {
+        return Ok(api);
+    }
+    let path = path();
+    let library = unsafe { libloading::Library::new(&path) }
+        .map_err(|error| format!("could not load {:?}: {}", path, error))?;
+    let api = &*Box::leak(Box::new(unsafe { Api::new(library) }?));
+    *cached = Some(api);
+    Ok(api)
+}
+
+/// Value returned by a runtime linked function that could not be loaded
+#[cfg(feature = "runtime-link")]
+trait Missing {
+    fn missing() -> Self;
+}
+
+#[cfg(feature = "runtime-link")]
+impl Missing for u32 {
+    fn missing() -> Self {
+        QHYCCD_ERROR
+    }
+}
+
+#[cfg(feature = "runtime-link")]
+impl Missing for f64 {
+    fn missing() -> Self {
+        QHYCCD_ERROR as f64
+    }
+}
+
+#[cfg(feature = "runtime-link")]
+impl Missing for QhyccdHandle {
+    fn missing() -> Self {
+        std::ptr::null_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CameraFeature;
+
+    fn control_id(feature: CameraFeature) -> CONTROL_ID {
+        match feature {
+            CameraFeature::ControlBrightness => CONTROL_ID_CONTROL_BRIGHTNESS,
+            CameraFeature::ControlContrast => CONTROL_ID_CONTROL_CONTRAST,
+            CameraFeature::ControlWbr => CONTROL_ID_CONTROL_WBR,
+            CameraFeature::ControlWbb => CONTROL_ID_CONTROL_WBB,
+            CameraFeature::ControlWbg => CONTROL_ID_CONTROL_WBG,
+            CameraFeature::ControlGamma => CONTROL_ID_CONTROL_GAMMA,
+            CameraFeature::ControlGain => CONTROL_ID_CONTROL_GAIN,
+            CameraFeature::ControlOffset => CONTROL_ID_CONTROL_OFFSET,
+            CameraFeature::ControlExposure => CONTROL_ID_CONTROL_EXPOSURE,
+            CameraFeature::ControlSpeed => CONTROL_ID_CONTROL_SPEED,
+            CameraFeature::ControlTransferBit => CONTROL_ID_CONTROL_TRANSFERBIT,
+            CameraFeature::ControlChannels => CONTROL_ID_CONTROL_CHANNELS,
+            CameraFeature::ControlUsbTraffic => CONTROL_ID_CONTROL_USBTRAFFIC,
+            CameraFeature::ControlRowDeNoise => CONTROL_ID_CONTROL_ROWNOISERE,
+            CameraFeature::ControlCurTemp => CONTROL_ID_CONTROL_CURTEMP,
+            CameraFeature::ControlCurPWM => CONTROL_ID_CONTROL_CURPWM,
+            CameraFeature::ControlManulPWM => CONTROL_ID_CONTROL_MANULPWM,
+            CameraFeature::ControlCfwPort => CONTROL_ID_CONTROL_CFWPORT,
+            CameraFeature::ControlCooler => CONTROL_ID_CONTROL_COOLER,
+            CameraFeature::ControlSt4Port => CONTROL_ID_CONTROL_ST4PORT,
+            CameraFeature::CamColor => CONTROL_ID_CAM_COLOR,
+            CameraFeature::CamBin1x1mode => CONTROL_ID_CAM_BIN1X1MODE,
+            CameraFeature::CamBin2x2mode => CONTROL_ID_CAM_BIN2X2MODE,
+            CameraFeature::CamBin3x3mode => CONTROL_ID_CAM_BIN3X3MODE,
+            CameraFeature::CamBin4x4mode => CONTROL_ID_CAM_BIN4X4MODE,
+            CameraFeature::CamMechanicalShutter => CONTROL_ID_CAM_MECHANICALSHUTTER,
+            CameraFeature::CamTrigerInterface => CONTROL_ID_CAM_TRIGER_INTERFACE,
+            CameraFeature::CamTecoverprotectInterface => CONTROL_ID_CAM_TECOVERPROTECT_INTERFACE,
+            CameraFeature::CamSignalClampInterface => CONTROL_ID_CAM_SINGNALCLAMP_INTERFACE,
+            CameraFeature::CamFinetoneInterface => CONTROL_ID_CAM_FINETONE_INTERFACE,
+            CameraFeature::CamShutterMotorHeatingInterface => {
+                CONTROL_ID_CAM_SHUTTERMOTORHEATING_INTERFACE
+            }
+            CameraFeature::CamCalibrateFpnInterface => CONTROL_ID_CAM_CALIBRATEFPN_INTERFACE,
+            CameraFeature::CamChipTemperatureSensorInterface => {
+                CONTROL_ID_CAM_CHIPTEMPERATURESENSOR_INTERFACE
+            }
+            CameraFeature::CamUsbReadoutSlowestInterface => {
+                CONTROL_ID_CAM_USBREADOUTSLOWEST_INTERFACE
+            }
+            CameraFeature::Cam8bits => CONTROL_ID_CAM_8BITS,
+            CameraFeature::Cam16bits => CONTROL_ID_CAM_16BITS,
+            CameraFeature::CamGps => CONTROL_ID_CAM_GPS,
+            CameraFeature::CamIgnoreOverscanInterface => CONTROL_ID_CAM_IGNOREOVERSCAN_INTERFACE,
+            CameraFeature::Qhyccd3aAutobalance => CONTROL_ID_QHYCCD_3A_AUTOBALANCE,
+            CameraFeature::Qhyccd3aAutoexposure => CONTROL_ID_QHYCCD_3A_AUTOEXPOSURE,
+            CameraFeature::Qhyccd3aAutofocus => CONTROL_ID_QHYCCD_3A_AUTOFOCUS,
+            CameraFeature::ControlAmpv => CONTROL_ID_CONTROL_AMPV,
+            CameraFeature::ControlVcam => CONTROL_ID_CONTROL_VCAM,
+            CameraFeature::CamViewMode => CONTROL_ID_CAM_VIEW_MODE,
+            CameraFeature::ControlCfwSlotsNum => CONTROL_ID_CONTROL_CFWSLOTSNUM,
+            CameraFeature::IsExposingDone => CONTROL_ID_IS_EXPOSING_DONE,
+            CameraFeature::ScreenStretchB => CONTROL_ID_ScreenStretchB,
+            CameraFeature::ScreenStretchW => CONTROL_ID_ScreenStretchW,
+            CameraFeature::ControlDDR => CONTROL_ID_CONTROL_DDR,
+            CameraFeature::CamLightPerformanceMode => CONTROL_ID_CAM_LIGHT_PERFORMANCE_MODE,
+            CameraFeature::CamQhy5iiGuideMode => CONTROL_ID_CAM_QHY5II_GUIDE_MODE,
+            CameraFeature::DDRBufferCapacity => CONTROL_ID_DDR_BUFFER_CAPACITY,
+            CameraFeature::DDRBufferReadThreshold => CONTROL_ID_DDR_BUFFER_READ_THRESHOLD,
+            CameraFeature::DefaultGain => CONTROL_ID_DefaultGain,
+            CameraFeature::DefaultOffset => CONTROL_ID_DefaultOffset,
+            CameraFeature::OutputDataActualBits => CONTROL_ID_OutputDataActualBits,
+            CameraFeature::OutputDataAlignment => CONTROL_ID_OutputDataAlignment,
+            CameraFeature::CamSingleFrameMode => CONTROL_ID_CAM_SINGLEFRAMEMODE,
+            CameraFeature::CamLiveVideoMode => CONTROL_ID_CAM_LIVEVIDEOMODE,
+            CameraFeature::CamIsColor => CONTROL_ID_CAM_IS_COLOR,
+            CameraFeature::HasHardwareFrameCounter => CONTROL_ID_hasHardwareFrameCounter,
+            CameraFeature::ControlMaxIdError => CONTROL_ID_CONTROL_MAX_ID_Error,
+            CameraFeature::CamHumidity => CONTROL_ID_CAM_HUMIDITY,
+            CameraFeature::CamPressure => CONTROL_ID_CAM_PRESSURE,
+            CameraFeature::ControlVacuumPump => CONTROL_ID_CONTROL_VACUUM_PUMP,
+            CameraFeature::ControlSensorChamberCyclePump => {
+                CONTROL_ID_CONTROL_SensorChamberCycle_PUMP
+            }
+            CameraFeature::Cam32bits => CONTROL_ID_CAM_32BITS,
+            CameraFeature::CamSensorUlvoStatus => CONTROL_ID_CAM_Sensor_ULVO_Status,
+            CameraFeature::CamSensorPhaseReTrain => CONTROL_ID_CAM_SensorPhaseReTrain,
+            CameraFeature::CamInitConfigFromFlash => CONTROL_ID_CAM_InitConfigFromFlash,
+            CameraFeature::CamTriggerMode => CONTROL_ID_CAM_TRIGER_MODE,
+            CameraFeature::CamTriggerOut => CONTROL_ID_CAM_TRIGER_OUT,
+            CameraFeature::CamBurstMode => CONTROL_ID_CAM_BURST_MODE,
+            CameraFeature::CamSpeakerLedAlarm => CONTROL_ID_CAM_SPEAKER_LED_ALARM,
+            CameraFeature::CamWatchDogFpga => CONTROL_ID_CAM_WATCH_DOG_FPGA,
+            CameraFeature::CamBin6x6mode => CONTROL_ID_CAM_BIN6X6MODE,
+            CameraFeature::CamBin8x8mode => CONTROL_ID_CAM_BIN8X8MODE,
+            CameraFeature::CamGlobalSensorGpsLED => CONTROL_ID_CAM_GlobalSensorGPSLED,
+            CameraFeature::ControlImgProc => CONTROL_ID_CONTROL_ImgProc,
+            CameraFeature::ControlRemoveRbi => CONTROL_ID_CONTROL_RemoveRBI,
+            CameraFeature::ControlGlobalReset => CONTROL_ID_CONTROL_GlobalReset,
+            CameraFeature::ControlFrameDetect => CONTROL_ID_CONTROL_FrameDetect,
+            CameraFeature::CamGainDbConversion => CONTROL_ID_CAM_GainDBConversion,
+            CameraFeature::CamCurveSystemGain => CONTROL_ID_CAM_CurveSystemGain,
+            CameraFeature::CamCurveFullWell => CONTROL_ID_CAM_CurveFullWell,
+            CameraFeature::CamCurveReadoutNoise => CONTROL_ID_CAM_CurveReadoutNoise,
+            CameraFeature::ControlMaxId => CONTROL_ID_CONTROL_MAX_ID,
+            CameraFeature::ControlAutowhitebalance => CONTROL_ID_CONTROL_AUTOWHITEBALANCE,
+            CameraFeature::ControlAutoexposure => CONTROL_ID_CONTROL_AUTOEXPOSURE,
+            CameraFeature::ControlAutoexpMessureValue => CONTROL_ID_CONTROL_AUTOEXPmessureValue,
+            CameraFeature::ControlAutoexpMessureMethod => CONTROL_ID_CONTROL_AUTOEXPmessureMethod,
+            CameraFeature::ControlImageStabilization => CONTROL_ID_CONTROL_ImageStabilization,
+            CameraFeature::ControlGaindB => CONTROL_ID_CONTROL_GAINdB,
+        }
+    }
+
+    #[test]
+    fn camera_feature_matches_control_id() {
+        let features = (0..=CONTROL_ID_CONTROL_GAINdB)
+            .filter_map(|id| CameraFeature::try_from(id).ok())
+            .collect::<Vec<_>>();
+        assert_eq!(features.len(), 93);
+        for feature in features {
+            assert_eq!(feature as u32, control_id(feature), "{:?}", feature);
+        }
+    }
+
+    #[cfg(feature = "runtime-link")]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn failed_load_is_not_cached() {
+        assert!(load_api(|| "/nonexistent/libqhyccd.so".into()).is_err());
+        assert!(API.lock().unwrap().is_none());
+        // the next load tries the library again
+        let mut retried = false;
+        let result = load_api(|| {
+            retried = true;
+            "/nonexistent/libqhyccd.so".into()
+        });
+        assert!(result.is_err() && retried);
+    }
 }
@@ -0,0 +1,537 @@
+//! Injecting SDK failures into a backend to exercise error handling
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{
+    bindings, CCDChipArea, CCDChipInfo, CameraBackend, CameraFeature, HdrCombine, ImageData,
+    QhyccdHandle, SdkBackend, SdkFunction,
+};
+
+/// SDK functions that keep working without a connected camera
+const SDK_FUNCTIONS: [SdkFunction; 3] = [
+    SdkFunction::InitQHYCCDResource,
+    SdkFunction::ReleaseQHYCCDResource,
+    SdkFunction::GetQHYCCDSDKVersion,
+];
+
+/// A failure `FaultInjector` applies to the calls passing through it
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Fault {
+    /// Return `code` from the `nth` call of `function`, counting from 1
+    FailNth {
+        function: SdkFunction,
+        nth: u64,
+        code: u32,
+    },
+    /// Return `code` from every `SetQHYCCDParam` for `feature`
+    FailParameter { feature: CameraFeature, code: u32 },
+    /// Unplug the camera `after` the fault was injected, `ScanQHYCCD` finds no camera and all
+    /// calls on a camera fail with `QHYCCD_ERROR`
+    DisconnectAfter(Duration),
+    /// Leave the image data of the `nth` frame read blank after `rows` rows, like a short USB
+    /// transfer, the call still succeeds and the frame header is unchanged
+    TruncateFrame { nth: u64, rows: u32 },
+}
+
+#[derive(Debug, Default)]
+struct FaultState {
+    faults: Vec<Fault>,
+    disconnect_at: Option<Instant>,
+    calls: HashMap<SdkFunction, u64>,
+    frames: u64,
+    injected: Vec<SdkFunction>,
+}
+
+impl FaultState {
+    fn is_disconnected(&self) -> bool {
+        self.disconnect_at.map_or(false, |at| Instant::now() >= at)
+    }
+
+    /// Count the call and return the code to answer it with instead of calling the backend
+    fn fault(&mut self, function: SdkFunction, feature: Option<CameraFeature>) -> Option<u32> {
+        let count = self.calls.entry(function).or_default();
+        *count += 1;
+        let count = *count;
+        let code = if self.is_disconnected() && !SDK_FUNCTIONS.contains(&function) {
+            match function {
+                SdkFunction::ScanQHYCCD => Some(0),
+                _ => Some(bindings::QHYCCD_ERROR),
+            }
+        } else {
+            self.faults.iter().find_map(|fault| match *fault {
+                Fault::FailNth {
+                    function: failing,
+                    nth,
+                    code,
+                } if failing == function && nth == count => Some(code),
+                Fault::FailParameter {
+                    feature: failing,
+                    code,
+                } if function == SdkFunction::SetQHYCCDParam && Some(failing) == feature => {
+                    Some(code)
+                }
+                _ => None,
+            })
+        };
+        if let Some(code) = code {
+            tracing::warn!(function = function.name(), code, "injected fault");
+            self.injected.push(function);
+        }
+        code
+    }
+}
+
+/// `CameraBackend` wrapper failing calls according to `Fault` rules
+///
+/// Clones share their state, so a test keeps one clone to inject faults while the code under
+/// test runs and hands another to `set_backend`.
+///
+/// # Example
+///
+/// ```
+/// # use libqhyccd_sys::{
+/// #     begin_live, get_camera_id, get_image_size, get_live_frame, init_sdk, open_camera,
+/// #     scan_qhyccd, set_backend, Fault, FaultInjector, MockBackend, SdkFunction,
+/// # };
+/// let faults = FaultInjector::wrap(MockBackend::new()).with_fault(Fault::FailNth {
+///     function: SdkFunction::GetQHYCCDLiveFrame,
+///     nth: 2,
+///     code: 0xFFFF_FFFF,
+/// });
+/// let _backend = set_backend(faults.clone());
+///
+/// init_sdk().unwrap();
+/// scan_qhyccd().unwrap();
+/// let camera = open_camera(get_camera_id(0).unwrap()).unwrap();
+/// begin_live(camera).unwrap();
+/// let size = get_image_size(camera).unwrap();
+/// assert!(get_live_frame(camera, size).is_ok());
+/// assert!(get_live_frame(camera, size).is_err());
+/// assert_eq!(faults.injected(), [SdkFunction::GetQHYCCDLiveFrame]);
+/// ```
+#[derive(Debug)]
+pub struct FaultInjector<B = SdkBackend> {
+    inner: Arc<B>,
+    state: Arc<Mutex<FaultState>>,
+}
+
+impl<B> Clone for FaultInjector<B> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl FaultInjector<SdkBackend> {
+    /// Inject faults into the calls made to the QHYCCD SDK
+    pub fn new() -> Self {
+        Self::wrap(SdkBackend)
+    }
+}
+
+impl Default for FaultInjector<SdkBackend> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: CameraBackend> FaultInjector<B> {
+    pub fn wrap(inner: B) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            state: Arc::new(Mutex::new(FaultState::default())),
+        }
+    }
+
+    pub fn with_fault(self, fault: Fault) -> Self {
+        self.inject(fault);
+        self
+    }
+
+    /// Add a fault while calls are already passing through, call counts start at creation
+    pub fn inject(&self, fault: Fault) {
+        let mut state = self.lock();
+        match fault {
+            Fault::DisconnectAfter(after) => state.disconnect_at = Some(Instant::now() + after),
+            fault => state.faults.push(fault),
+        }
+    }
+
+    /// Unplug the camera now
+    pub fn disconnect(&self) {
+        self.lock().disconnect_at = Some(Instant::now());
+    }
+
+    /// Plug the camera back in, it has to be opened again like real hardware
+    pub fn reconnect(&self) {
+        self.lock().disconnect_at = None;
+    }
+
+    /// Remove all faults and reconnect the camera
+    pub fn clear(&self) {
+        let mut state = self.lock();
+        state.faults.clear();
+        state.disconnect_at = None;
+    }
+
+    /// SDK functions a fault was injected into, in order
+    pub fn injected(&self) -> Vec<SdkFunction> {
+        self.lock().injected.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FaultState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Call `f` unless a fault applies, whose code is turned into the result by `error`
+    fn call<T>(
+        &self,
+        function: SdkFunction,
+        feature: Option<CameraFeature>,
+        error: impl FnOnce(u32) -> T,
+        f: impl FnOnce(&B) -> T,
+    ) -> T {
+        let fault = self.lock().fault(function, feature);
+        match fault {
+            Some(code) => error(code),
+            None => f(&self.inner),
+        }
+    }
+
+    fn code(&self, function: SdkFunction, f: impl FnOnce(&B) -> u32) -> u32 {
+        self.call(function, None, |code| code, f)
+    }
+
+    /// Read a frame with `f` and apply `TruncateFrame` faults to it
+    fn frame(
+        &self,
+        function: SdkFunction,
+        image: &mut ImageData,
+        f: impl FnOnce(&B, &mut ImageData) -> u32,
+    ) -> u32 {
+        let result = self.code(function, |inner| f(inner, image));
+        if result != bindings::QHYCCD_SUCCESS {
+            return result;
+        }
+        let mut state = self.lock();
+        state.frames += 1;
+        let frames = state.frames;
+        let rows = state.faults.iter().find_map(|fault| match *fault {
+            Fault::TruncateFrame { nth, rows } if nth == frames => Some(rows),
+            _ => None,
+        });
+        if let Some(rows) = rows {
+            let row_length = image.width as usize * image.bytes_per_pixel();
+            let end = (row_length * image.height as usize).min(image.data.len());
+            let start = (row_length * rows as usize).min(end);
+            image.data[start..end].fill(0);
+            tracing::warn!(function = function.name(), rows, "injected truncated frame");
+            state.injected.push(function);
+        }
+        result
+    }
+}
+
+impl<B: CameraBackend> CameraBackend for FaultInjector<B> {
+    fn load(&self) -> Result<(), String> {
+        self.inner.load()
+    }
+
+    fn is_available(&self, function: SdkFunction) -> bool {
+        self.inner.is_available(function)
+    }
+
+    fn init_qhyccd_resource(&self) -> u32 {
+        self.code(SdkFunction::InitQHYCCDResource, |inner| {
+            inner.init_qhyccd_resource()
+        })
+    }
+
+    fn release_qhyccd_resource(&self) -> u32 {
+        self.code(SdkFunction::ReleaseQHYCCDResource, |inner| {
+            inner.release_qhyccd_resource()
+        })
+    }
+
+    fn scan_qhyccd(&self) -> u32 {
+        self.code(SdkFunction::ScanQHYCCD, |inner| inner.scan_qhyccd())
+    }
+
+    fn get_qhyccd_sdk_version(
+        &self,
+        year: &mut u32,
+        month: &mut u32,
+        day: &mut u32,
+        subday: &mut u32,
+    ) -> u32 {
+        self.code(SdkFunction::GetQHYCCDSDKVersion, |inner| {
+            inner.get_qhyccd_sdk_version(year, month, day, subday)
+        })
+    }
+
+    fn get_qhyccd_id(&self, index: u32, id: &mut [u8]) -> u32 {
+        self.code(SdkFunction::GetQHYCCDId, |inner| {
+            inner.get_qhyccd_id(index, id)
+        })
+    }
+
+    fn open_qhyccd(&self, id: &str) -> QhyccdHandle {
+        self.call(
+            SdkFunction::OpenQHYCCD,
+            None,
+            |code| match code {
+                bindings::QHYCCD_ERROR => QhyccdHandle::new(std::ptr::null_mut()),
+                code => QhyccdHandle::new(code as usize as *mut c_void),
+            },
+            |inner| inner.open_qhyccd(id),
+        )
+    }
+
+    fn close_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::CloseQHYCCD, |inner| inner.close_qhyccd(handle))
+    }
+
+    fn init_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::InitQHYCCD, |inner| inner.init_qhyccd(handle))
+    }
+
+    fn get_qhyccd_fw_version(&self, handle: QhyccdHandle, version: &mut [u8]) -> u32 {
+        self.code(SdkFunction::GetQHYCCDFWVersion, |inner| {
+            inner.get_qhyccd_fw_version(handle, version)
+        })
+    }
+
+    fn get_qhyccd_model(&self, handle: QhyccdHandle, model: &mut [u8]) -> u32 {
+        self.code(SdkFunction::GetQHYCCDModel, |inner| {
+            inner.get_qhyccd_model(handle, model)
+        })
+    }
+
+    fn get_qhyccd_type(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::GetQHYCCDType, |inner| {
+            inner.get_qhyccd_type(handle)
+        })
+    }
+
+    fn is_qhyccd_control_available(&self, handle: QhyccdHandle, feature: CameraFeature) -> u32 {
+        self.code(SdkFunction::IsQHYCCDControlAvailable, |inner| {
+            inner.is_qhyccd_control_available(handle, feature)
+        })
+    }
+
+    fn set_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: u32) -> u32 {
+        self.code(SdkFunction::SetQHYCCDReadMode, |inner| {
+            inner.set_qhyccd_read_mode(handle, mode)
+        })
+    }
+
+    fn get_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: &mut u32) -> u32 {
+        self.code(SdkFunction::GetQHYCCDReadMode, |inner| {
+            inner.get_qhyccd_read_mode(handle, mode)
+        })
+    }
+
+    fn get_qhyccd_number_of_read_modes(&self, handle: QhyccdHandle, modes: &mut u32) -> u32 {
+        self.code(SdkFunction::GetQHYCCDNumberOfReadModes, |inner| {
+            inner.get_qhyccd_number_of_read_modes(handle, modes)
+        })
+    }
+
+    fn get_qhyccd_read_mode_name(&self, handle: QhyccdHandle, mode: u32, name: &mut [u8]) -> u32 {
+        self.code(SdkFunction::GetQHYCCDReadModeName, |inner| {
+            inner.get_qhyccd_read_mode_name(handle, mode, name)
+        })
+    }
+
+    fn get_qhyccd_read_mode_resolution(
+        &self,
+        handle: QhyccdHandle,
+        mode: u32,
+        width: &mut u32,
+        height: &mut u32,
+    ) -> u32 {
+        self.code(SdkFunction::GetQHYCCDReadModeResolution, |inner| {
+            inner.get_qhyccd_read_mode_resolution(handle, mode, width, height)
+        })
+    }
+
+    fn set_qhyccd_stream_mode(&self, handle: QhyccdHandle, mode: u8) -> u32 {
+        self.code(SdkFunction::SetQHYCCDStreamMode, |inner| {
+            inner.set_qhyccd_stream_mode(handle, mode)
+        })
+    }
+
+    fn get_qhyccd_chip_info(&self, handle: QhyccdHandle, info: &mut CCDChipInfo) -> u32 {
+        self.code(SdkFunction::GetQHYCCDChipInfo, |inner| {
+            inner.get_qhyccd_chip_info(handle, info)
+        })
+    }
+
+    fn get_qhyccd_overscan_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        self.code(SdkFunction::GetQHYCCDOverScanArea, |inner| {
+            inner.get_qhyccd_overscan_area(handle, area)
+        })
+    }
+
+    fn get_qhyccd_effective_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        self.code(SdkFunction::GetQHYCCDEffectiveArea, |inner| {
+            inner.get_qhyccd_effective_area(handle, area)
+        })
+    }
+
+    fn set_qhyccd_bits_mode(&self, handle: QhyccdHandle, bits: u32) -> u32 {
+        self.code(SdkFunction::SetQHYCCDBitsMode, |inner| {
+            inner.set_qhyccd_bits_mode(handle, bits)
+        })
+    }
+
+    fn set_qhyccd_debayer_on_off(&self, handle: QhyccdHandle, on: bool) -> u32 {
+        self.code(SdkFunction::SetQHYCCDDebayerOnOff, |inner| {
+            inner.set_qhyccd_debayer_on_off(handle, on)
+        })
+    }
+
+    fn set_qhyccd_bin_mode(&self, handle: QhyccdHandle, bin_x: u32, bin_y: u32) -> u32 {
+        self.code(SdkFunction::SetQHYCCDBinMode, |inner| {
+            inner.set_qhyccd_bin_mode(handle, bin_x, bin_y)
+        })
+    }
+
+    fn set_qhyccd_resolution(&self, handle: QhyccdHandle, roi: CCDChipArea) -> u32 {
+        self.code(SdkFunction::SetQHYCCDResolution, |inner| {
+            inner.set_qhyccd_resolution(handle, roi)
+        })
+    }
+
+    fn get_qhyccd_current_roi(&self, handle: QhyccdHandle, roi: &mut CCDChipArea) -> u32 {
+        self.code(SdkFunction::GetQHYCCDCurrentROI, |inner| {
+            inner.get_qhyccd_current_roi(handle, roi)
+        })
+    }
+
+    fn set_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature, value: f64) -> u32 {
+        self.call(
+            SdkFunction::SetQHYCCDParam,
+            Some(feature),
+            |code| code,
+            |inner| inner.set_qhyccd_param(handle, feature, value),
+        )
+    }
+
+    fn get_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature) -> f64 {
+        self.call(
+            SdkFunction::GetQHYCCDParam,
+            Some(feature),
+            |code| code as f64,
+            |inner| inner.get_qhyccd_param(handle, feature),
+        )
+    }
+
+    fn get_qhyccd_param_min_max_step(
+        &self,
+        handle: QhyccdHandle,
+        feature: CameraFeature,
+        min: &mut f64,
+        max: &mut f64,
+        step: &mut f64,
+    ) -> u32 {
+        self.call(
+            SdkFunction::GetQHYCCDParamMinMaxStep,
+            Some(feature),
+            |code| code,
+            |inner| inner.get_qhyccd_param_min_max_step(handle, feature, min, max, step),
+        )
+    }
+
+    fn get_qhyccd_mem_length(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::GetQHYCCDMemLength, |inner| {
+            inner.get_qhyccd_mem_length(handle)
+        })
+    }
+
+    fn exp_qhyccd_single_frame(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::ExpQHYCCDSingleFrame, |inner| {
+            inner.exp_qhyccd_single_frame(handle)
+        })
+    }
+
+    fn get_qhyccd_single_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        self.frame(SdkFunction::GetQHYCCDSingleFrame, image, |inner, image| {
+            inner.get_qhyccd_single_frame(handle, image)
+        })
+    }
+
+    fn begin_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::BeginQHYCCDLive, |inner| {
+            inner.begin_qhyccd_live(handle)
+        })
+    }
+
+    fn get_qhyccd_live_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        self.frame(SdkFunction::GetQHYCCDLiveFrame, image, |inner, image| {
+            inner.get_qhyccd_live_frame(handle, image)
+        })
+    }
+
+    fn stop_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::StopQHYCCDLive, |inner| {
+            inner.stop_qhyccd_live(handle)
+        })
+    }
+
+    fn get_qhyccd_exposure_remaining(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::GetQHYCCDExposureRemaining, |inner| {
+            inner.get_qhyccd_exposure_remaining(handle)
+        })
+    }
+
+    fn cancel_qhyccd_exposing(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::CancelQHYCCDExposing, |inner| {
+            inner.cancel_qhyccd_exposing(handle)
+        })
+    }
+
+    fn cancel_qhyccd_exposing_and_readout(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::CancelQHYCCDExposingAndReadout, |inner| {
+            inner.cancel_qhyccd_exposing_and_readout(handle)
+        })
+    }
+
+    fn get_qhyccd_humidity(&self, handle: QhyccdHandle, humidity: &mut f64) -> u32 {
+        self.code(SdkFunction::GetQHYCCDHumidity, |inner| {
+            inner.get_qhyccd_humidity(handle, humidity)
+        })
+    }
+
+    fn qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::QHYCCDSensorPhaseReTrain, |inner| {
+            inner.qhyccd_sensor_phase_retrain(handle)
+        })
+    }
+
+    fn get_qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        self.code(SdkFunction::GetQHYCCDSensorPhaseReTrain, |inner| {
+            inner.get_qhyccd_sensor_phase_retrain(handle)
+        })
+    }
+
+    fn set_qhyccd_enable_live_mode_anti_rbi(&self, handle: QhyccdHandle, value: u32) -> u32 {
+        self.code(SdkFunction::SetQHYCCDEnableLiveModeAntiRBI, |inner| {
+            inner.set_qhyccd_enable_live_mode_anti_rbi(handle, value)
+        })
+    }
+
+    fn set_qhyccd_two_channel_combine_parameter(
+        &self,
+        handle: QhyccdHandle,
+        combine: HdrCombine,
+    ) -> u32 {
+        self.code(SdkFunction::SetQHYCCDTwoChannelCombineParameter, |inner| {
+            inner.set_qhyccd_two_channel_combine_parameter(handle, combine)
+        })
+    }
+}
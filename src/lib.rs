@@ -1,14 +1,54 @@
-use std::ffi::c_char;
-use std::ffi::CStr;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
 
 use eyre::eyre;
 use eyre::Result;
 use thiserror::Error;
 
+mod backend;
 mod bindings;
+#[cfg(feature = "testing")]
+mod faults;
+mod locking;
+#[cfg(feature = "testing")]
+mod mock;
+#[cfg(feature = "testing")]
+mod simulator;
+#[cfg(feature = "testing")]
+mod trace;
+
+use backend::backend;
+pub use backend::{set_backend, BackendGuard, CameraBackend, SdkBackend};
+pub use bindings::SdkFunction;
+#[cfg(feature = "testing")]
+pub use faults::{Fault, FaultInjector};
+#[cfg(feature = "testing")]
+pub use mock::MockBackend;
+#[cfg(feature = "testing")]
+pub use simulator::{SensorModel, Star};
+#[cfg(feature = "testing")]
+pub use trace::{FrameRecording, RecordingBackend, ReplayBackend};
 
 #[derive(Error, Debug)]
 pub enum QHYError {
+    #[error("Error loading QHYCCD SDK, {}", reason)]
+    SdkNotFound { reason: String },
+    #[error("Error QHYCCD SDK {} or newer is required, found {}", required, found)]
+    UnsupportedBySdk {
+        required: SDKVersion,
+        found: SDKVersion,
+    },
+    #[error("Error QHYCCD SDK {} does not export {}", found, function)]
+    SdkFunctionMissing {
+        function: SdkFunction,
+        found: SDKVersion,
+    },
+    #[error("Error parsing QHYCCD SDK version {:?}", version)]
+    ParseSDKVersionError { version: String },
     #[error("Error initializing QHYCCD SDK, error code {}", error_code)]
     InitSDKError { error_code: u32 },
     #[error("Error closing QHYCCD SDK, error code {}", error_code)]
@@ -77,12 +117,99 @@ pub enum QHYError {
     StopExposureError { error_code: u32 },
     #[error("Error canceling exposure and readout {:?}", error_code)]
     AbortExposureAndReadoutError { error_code: u32 },
+    #[error("Error setting HDR two channel combine parameters {:?}", error_code)]
+    SetHdrCombineError { error_code: u32 },
+    #[error("Error combining HDR channels, both channels must be 16 bit images of the same size")]
+    CombineHdrChannelsError,
+    #[error("Error getting camera parameter {:?}", feature)]
+    GetParameterError { feature: CameraFeature },
+    #[error("Error getting camera parameter range {:?}", feature)]
+    GetParameterMinMaxStepError { feature: CameraFeature },
+    #[error("Error getting sensor chamber humidity {:?}", error_code)]
+    GetHumidityError { error_code: u32 },
+    #[error("Error starting sensor phase retrain {:?}", error_code)]
+    SensorPhaseReTrainError { error_code: u32 },
+    #[error("Error getting sensor phase retrain status")]
+    GetSensorPhaseReTrainError,
+    #[error("Error setting live mode anti RBI {:?}", error_code)]
+    SetLiveModeAntiRbiError { error_code: u32 },
+    #[error(
+        "Error value {} for camera parameter {:?} is outside of {}..={}",
+        value,
+        feature,
+        min,
+        max
+    )]
+    ParameterOutOfRangeError {
+        feature: CameraFeature,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    #[error(
+        "Error camera reported an invalid range {}..={} for parameter {:?}",
+        min,
+        max,
+        feature
+    )]
+    InvalidParameterRangeError {
+        feature: CameraFeature,
+        min: f64,
+        max: f64,
+    },
+    #[error("Error computing white balance, image must be a debayered 8 or 16 bit image")]
+    WhiteBalanceImageError,
+    #[error("Error camera does not support gain in dB")]
+    GainDbUnsupportedError,
+    #[error("Error cropping image, area {:?} is outside of the image", area)]
+    CropError { area: CCDChipArea },
+    #[error("Error camera has no readout mode named {:?}", name)]
+    ReadoutModeNotFoundError { name: String },
+    #[error("Error getting camera sub frame, error code {:?}", error_code)]
+    GetRoiError { error_code: u32 },
+    #[error("Error binning {:?} is not supported by the camera", binning)]
+    UnsupportedBinningError { binning: Binning },
+    #[error("Error sub frame {:?} is outside of the sensor", roi)]
+    RoiOutOfBoundsError { roi: CCDChipArea },
+    #[error("Error sub frame {:?} size must be a multiple of {}", roi, alignment)]
+    RoiAlignmentError { roi: CCDChipArea, alignment: u32 },
+    #[error("Error bit depth {:?} is not supported by the camera", bit_depth)]
+    UnsupportedBitDepthError { bit_depth: BitDepth },
+    #[error("Error camera does not support {:?}", feature)]
+    UnsupportedFeatureError { feature: CameraFeature },
+    #[error(
+        "Error exposure {:?} is outside of the camera range {:?}..={:?}",
+        exposure,
+        min,
+        max
+    )]
+    ExposureOutOfRangeError {
+        exposure: Duration,
+        min: Duration,
+        max: Duration,
+    },
+    #[error("Error parsing SDK call trace at line {}", line)]
+    ParseTraceError { line: usize },
 }
+/// Handle of an open camera
+///
+/// Handles can be shared between threads, the crate serializes the SDK calls made with them:
+///
+/// - calls on one camera run one at a time, calls on different cameras run concurrently
+/// - `init_sdk`, `release_sdk`, `scan_qhyccd`, `get_camera_id`, `get_sdk_version`,
+///   `open_camera` and `close_camera` wait for all running calls and block all others
+/// - `get_remaining_exposure_us`, `stop_exposure` and `abort_exposure_and_readout` do not wait
+///   for other calls on the camera, so they can interrupt `get_single_frame`
+///
+/// `get_single_frame` holds its camera for the whole exposure. Using a handle after
+/// `close_camera` or `release_sdk` fails in the SDK like in a single thread.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct QhyccdHandle {
     ptr: bindings::QhyccdHandle,
 }
 
+// SAFETY: the handle is an opaque pointer the crate never dereferences, it is only passed to
+// the SDK and every such call goes through the locks of `locking::Serialized`
 unsafe impl Send for QhyccdHandle {}
 unsafe impl Sync for QhyccdHandle {}
 
@@ -132,6 +259,7 @@ pub enum CameraFeature {
     Cam16bits = 35,
     CamGps = 36,
     CamIgnoreOverscanInterface = 37,
+    Qhyccd3aAutobalance = 38,
     Qhyccd3aAutoexposure = 39,
     Qhyccd3aAutofocus = 40,
     ControlAmpv = 41,
@@ -188,13 +316,116 @@ pub enum CameraFeature {
     ControlGaindB = 1029,
 }
 
+impl TryFrom<u32> for CameraFeature {
+    type Error = u32;
+
+    fn try_from(value: u32) -> std::result::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(CameraFeature::ControlBrightness),
+            1 => Ok(CameraFeature::ControlContrast),
+            2 => Ok(CameraFeature::ControlWbr),
+            3 => Ok(CameraFeature::ControlWbb),
+            4 => Ok(CameraFeature::ControlWbg),
+            5 => Ok(CameraFeature::ControlGamma),
+            6 => Ok(CameraFeature::ControlGain),
+            7 => Ok(CameraFeature::ControlOffset),
+            8 => Ok(CameraFeature::ControlExposure),
+            9 => Ok(CameraFeature::ControlSpeed),
+            10 => Ok(CameraFeature::ControlTransferBit),
+            11 => Ok(CameraFeature::ControlChannels),
+            12 => Ok(CameraFeature::ControlUsbTraffic),
+            13 => Ok(CameraFeature::ControlRowDeNoise),
+            14 => Ok(CameraFeature::ControlCurTemp),
+            15 => Ok(CameraFeature::ControlCurPWM),
+            16 => Ok(CameraFeature::ControlManulPWM),
+            17 => Ok(CameraFeature::ControlCfwPort),
+            18 => Ok(CameraFeature::ControlCooler),
+            19 => Ok(CameraFeature::ControlSt4Port),
+            20 => Ok(CameraFeature::CamColor),
+            21 => Ok(CameraFeature::CamBin1x1mode),
+            22 => Ok(CameraFeature::CamBin2x2mode),
+            23 => Ok(CameraFeature::CamBin3x3mode),
+            24 => Ok(CameraFeature::CamBin4x4mode),
+            25 => Ok(CameraFeature::CamMechanicalShutter),
+            26 => Ok(CameraFeature::CamTrigerInterface),
+            27 => Ok(CameraFeature::CamTecoverprotectInterface),
+            28 => Ok(CameraFeature::CamSignalClampInterface),
+            29 => Ok(CameraFeature::CamFinetoneInterface),
+            30 => Ok(CameraFeature::CamShutterMotorHeatingInterface),
+            31 => Ok(CameraFeature::CamCalibrateFpnInterface),
+            32 => Ok(CameraFeature::CamChipTemperatureSensorInterface),
+            33 => Ok(CameraFeature::CamUsbReadoutSlowestInterface),
+            34 => Ok(CameraFeature::Cam8bits),
+            35 => Ok(CameraFeature::Cam16bits),
+            36 => Ok(CameraFeature::CamGps),
+            37 => Ok(CameraFeature::CamIgnoreOverscanInterface),
+            38 => Ok(CameraFeature::Qhyccd3aAutobalance),
+            39 => Ok(CameraFeature::Qhyccd3aAutoexposure),
+            40 => Ok(CameraFeature::Qhyccd3aAutofocus),
+            41 => Ok(CameraFeature::ControlAmpv),
+            42 => Ok(CameraFeature::ControlVcam),
+            43 => Ok(CameraFeature::CamViewMode),
+            44 => Ok(CameraFeature::ControlCfwSlotsNum),
+            45 => Ok(CameraFeature::IsExposingDone),
+            46 => Ok(CameraFeature::ScreenStretchB),
+            47 => Ok(CameraFeature::ScreenStretchW),
+            48 => Ok(CameraFeature::ControlDDR),
+            49 => Ok(CameraFeature::CamLightPerformanceMode),
+            50 => Ok(CameraFeature::CamQhy5iiGuideMode),
+            51 => Ok(CameraFeature::DDRBufferCapacity),
+            52 => Ok(CameraFeature::DDRBufferReadThreshold),
+            53 => Ok(CameraFeature::DefaultGain),
+            54 => Ok(CameraFeature::DefaultOffset),
+            55 => Ok(CameraFeature::OutputDataActualBits),
+            56 => Ok(CameraFeature::OutputDataAlignment),
+            57 => Ok(CameraFeature::CamSingleFrameMode),
+            58 => Ok(CameraFeature::CamLiveVideoMode),
+            59 => Ok(CameraFeature::CamIsColor),
+            60 => Ok(CameraFeature::HasHardwareFrameCounter),
+            61 => Ok(CameraFeature::ControlMaxIdError),
+            62 => Ok(CameraFeature::CamHumidity),
+            63 => Ok(CameraFeature::CamPressure),
+            64 => Ok(CameraFeature::ControlVacuumPump),
+            65 => Ok(CameraFeature::ControlSensorChamberCyclePump),
+            66 => Ok(CameraFeature::Cam32bits),
+            67 => Ok(CameraFeature::CamSensorUlvoStatus),
+            68 => Ok(CameraFeature::CamSensorPhaseReTrain),
+            69 => Ok(CameraFeature::CamInitConfigFromFlash),
+            70 => Ok(CameraFeature::CamTriggerMode),
+            71 => Ok(CameraFeature::CamTriggerOut),
+            72 => Ok(CameraFeature::CamBurstMode),
+            73 => Ok(CameraFeature::CamSpeakerLedAlarm),
+            74 => Ok(CameraFeature::CamWatchDogFpga),
+            75 => Ok(CameraFeature::CamBin6x6mode),
+            76 => Ok(CameraFeature::CamBin8x8mode),
+            77 => Ok(CameraFeature::CamGlobalSensorGpsLED),
+            78 => Ok(CameraFeature::ControlImgProc),
+            79 => Ok(CameraFeature::ControlRemoveRbi),
+            80 => Ok(CameraFeature::ControlGlobalReset),
+            81 => Ok(CameraFeature::ControlFrameDetect),
+            82 => Ok(CameraFeature::CamGainDbConversion),
+            83 => Ok(CameraFeature::CamCurveSystemGain),
+            84 => Ok(CameraFeature::CamCurveFullWell),
+            85 => Ok(CameraFeature::CamCurveReadoutNoise),
+            86 => Ok(CameraFeature::ControlMaxId),
+            1024 => Ok(CameraFeature::ControlAutowhitebalance),
+            1025 => Ok(CameraFeature::ControlAutoexposure),
+            1026 => Ok(CameraFeature::ControlAutoexpMessureValue),
+            1027 => Ok(CameraFeature::ControlAutoexpMessureMethod),
+            1028 => Ok(CameraFeature::ControlImageStabilization),
+            1029 => Ok(CameraFeature::ControlGaindB),
+            other => Err(other),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum CameraStreamMode {
     SingleFrameMode = 0,
     LiveMode = 1,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
 pub struct CCDChipInfo {
     pub chip_width: f64,
     pub chip_height: f64,
@@ -205,7 +436,7 @@ pub struct CCDChipInfo {
     pub bits_per_pixel: u32,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct ImageData {
     pub data: Vec<u8>,
     pub width: u32,
@@ -214,7 +445,381 @@ pub struct ImageData {
     pub channels: u32,
 }
 
+impl ImageData {
+    fn bytes_per_sample(&self) -> usize {
+        match self.bits_per_pixel {
+            0..=8 => 1,
+            9..=16 => 2,
+            _ => 4,
+        }
+    }
+
+    fn bytes_per_pixel(&self) -> usize {
+        self.bytes_per_sample() * self.channels.max(1) as usize
+    }
+
+    /// Bit depth of the pixel buffer, `None` for layouts other than 8, 16 or 32 bit
+    pub fn bit_depth(&self) -> Option<BitDepth> {
+        match self.bits_per_pixel {
+            8 => Some(BitDepth::Eight),
+            16 => Some(BitDepth::Sixteen),
+            32 => Some(BitDepth::ThirtyTwo),
+            _ => None,
+        }
+    }
+
+    /// Copy the given area out of the image
+    pub fn crop(&self, area: CCDChipArea) -> Result<ImageData> {
+        let bytes_per_pixel = self.bytes_per_pixel();
+        let row_length = self.width as usize * bytes_per_pixel;
+        if area.start_x as u64 + area.width as u64 > self.width as u64
+            || area.start_y as u64 + area.height as u64 > self.height as u64
+            || row_length * self.height as usize > self.data.len()
+        {
+            let error = QHYError::CropError { area };
+            tracing::error!(error = error.to_string().as_str());
+            return Err(eyre!(error));
+        }
+        let start = area.start_x as usize * bytes_per_pixel;
+        let end = start + area.width as usize * bytes_per_pixel;
+        let data = match row_length {
+            0 => Vec::new(),
+            row_length => self
+                .data
+                .chunks_exact(row_length)
+                .skip(area.start_y as usize)
+                .take(area.height as usize)
+                .flat_map(|row| &row[start..end])
+                .copied()
+                .collect(),
+        };
+        Ok(ImageData {
+            data,
+            width: area.width,
+            height: area.height,
+            bits_per_pixel: self.bits_per_pixel,
+            channels: self.channels,
+        })
+    }
+
+    /// Mean value of all samples in the image
+    pub fn mean(&self) -> f64 {
+        let bytes_per_sample = self.bytes_per_sample();
+        let sum = self
+            .data
+            .chunks_exact(bytes_per_sample)
+            .map(|sample| match bytes_per_sample {
+                1 => sample[0] as f64,
+                2 => u16::from_le_bytes([sample[0], sample[1]]) as f64,
+                _ => u32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]) as f64,
+            })
+            .sum::<f64>();
+        let count = self.data.len() / bytes_per_sample;
+        match count {
+            0 => 0.0,
+            count => sum / count as f64,
+        }
+    }
+}
+
+/// Sequence number of a live frame
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FrameNumber {
+    /// frame counter reported by cameras supporting `CameraFeature::HasHardwareFrameCounter`
+    Hardware(u32),
+    /// sequence counted on the host, used when the camera has no hardware frame counter
+    Host(u32),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LiveFrame {
+    pub image: ImageData,
+    pub frame_number: FrameNumber,
+}
+
+/// Frame statistics of a live session, gaps are only detectable with a hardware frame counter
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct LiveStatistics {
+    pub delivered: u64,
+    pub dropped: u64,
+    pub duplicated: u64,
+}
+
+/// Fill state of the onboard DDR frame buffer
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DdrBuffer {
+    pub capacity: f64,
+    pub fill: f64,
+}
+
+impl DdrBuffer {
+    /// fraction of the buffer in use, between 0.0 and 1.0
+    pub fn fill_ratio(&self) -> f64 {
+        match self.capacity > 0.0 {
+            true => self.fill / self.capacity,
+            false => 0.0,
+        }
+    }
+}
+
+/// Runs the sensor chamber cycle pump while the chamber humidity is too high
+///
+/// The pump is switched on once the humidity exceeds `threshold` and switched off again when it
+/// drops below `threshold - hysteresis`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CyclePumpPolicy {
+    pub threshold: f64,
+    pub hysteresis: f64,
+}
+
+impl CyclePumpPolicy {
+    /// Read the chamber humidity and switch the cycle pump accordingly, returns whether the pump
+    /// is running afterwards
+    pub fn apply(&self, handle: QhyccdHandle) -> Result<bool> {
+        let humidity = get_humidity(handle)?;
+        let running = is_cycle_pump_on(handle)?;
+        let run = match running {
+            true => humidity >= self.threshold - self.hysteresis,
+            false => humidity > self.threshold,
+        };
+        if run != running {
+            tracing::debug!(humidity = humidity, cycle_pump = run);
+            set_cycle_pump(handle, run)?;
+        }
+        Ok(run)
+    }
+}
+
+/// Under voltage lockout state of the sensor as reported by `CameraFeature::CamSensorUlvoStatus`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum UlvoStatus {
+    Normal,
+    UnderVoltage,
+    Unknown(u32),
+}
+
+impl From<u32> for UlvoStatus {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => UlvoStatus::Normal,
+            1 => UlvoStatus::UnderVoltage,
+            other => UlvoStatus::Unknown(other),
+        }
+    }
+}
+
+/// Health of the sensor, statuses the camera does not support are reported as healthy
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SensorHealth {
+    pub ulvo: UlvoStatus,
+    pub phase_retrain_pending: bool,
+    pub fpga_watchdog_triggered: bool,
+}
+
+impl SensorHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.ulvo == UlvoStatus::Normal
+            && !self.phase_retrain_pending
+            && !self.fpga_watchdog_triggered
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SensorHealthEvent {
+    /// the health changed, `previous` is `None` for the first reading
+    Changed {
+        previous: Option<SensorHealth>,
+        current: SensorHealth,
+    },
+    /// the health could not be read from the camera
+    Unavailable,
+}
+
+/// Residual bulk image mitigation applied before each single frame exposure
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct RbiMitigation {
+    /// let the camera pre-flash the sensor through `CameraFeature::ControlRemoveRbi`
+    pub pre_flash: bool,
+    /// number of exposures taken and discarded to flush the sensor before the actual exposure
+    pub flush_frames: u32,
+}
+
+/// How `capture_single_frame` treats the overscan area of the sensor
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Overscan {
+    /// return the frame as read out, including overscan
+    #[default]
+    Include,
+    /// let the SDK skip the overscan through `CameraFeature::CamIgnoreOverscanInterface` when
+    /// supported, otherwise crop the frame to the effective area
+    Remove,
+    /// crop the frame to the effective area and return the overscan strip separately
+    RemoveKeepStrip,
+}
+
+/// Options for `capture_single_frame`, can be reused for every frame of an exposure sequence
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct SingleFrameOptions {
+    pub rbi_mitigation: Option<RbiMitigation>,
+    pub overscan: Overscan,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SingleFrame {
+    pub image: ImageData,
+    /// the overscan strip when captured with `Overscan::RemoveKeepStrip`
+    pub overscan: Option<ImageData>,
+}
+
+impl SingleFrame {
+    /// Bias level estimated as the mean of the overscan strip
+    pub fn bias_level(&self) -> Option<f64> {
+        self.overscan.as_ref().map(ImageData::mean)
+    }
+}
+
+/// White balance gains of a color camera
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct WhiteBalance {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BitDepth {
+    Eight,
+    Sixteen,
+    ThirtyTwo,
+}
+
+impl BitDepth {
+    pub fn bits(self) -> u32 {
+        match self {
+            BitDepth::Eight => 8,
+            BitDepth::Sixteen => 16,
+            BitDepth::ThirtyTwo => 32,
+        }
+    }
+
+    /// the feature a camera reports when it supports this bit depth
+    pub fn feature(self) -> CameraFeature {
+        match self {
+            BitDepth::Eight => CameraFeature::Cam8bits,
+            BitDepth::Sixteen => CameraFeature::Cam16bits,
+            BitDepth::ThirtyTwo => CameraFeature::Cam32bits,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Binning {
+    Bin1x1,
+    Bin2x2,
+    Bin3x3,
+    Bin4x4,
+    Bin6x6,
+    Bin8x8,
+}
+
+impl Binning {
+    pub const ALL: [Binning; 6] = [
+        Binning::Bin1x1,
+        Binning::Bin2x2,
+        Binning::Bin3x3,
+        Binning::Bin4x4,
+        Binning::Bin6x6,
+        Binning::Bin8x8,
+    ];
+
+    pub fn factor(self) -> u32 {
+        match self {
+            Binning::Bin1x1 => 1,
+            Binning::Bin2x2 => 2,
+            Binning::Bin3x3 => 3,
+            Binning::Bin4x4 => 4,
+            Binning::Bin6x6 => 6,
+            Binning::Bin8x8 => 8,
+        }
+    }
+
+    /// the feature a camera reports when it supports this binning
+    pub fn feature(self) -> CameraFeature {
+        match self {
+            Binning::Bin1x1 => CameraFeature::CamBin1x1mode,
+            Binning::Bin2x2 => CameraFeature::CamBin2x2mode,
+            Binning::Bin3x3 => CameraFeature::CamBin3x3mode,
+            Binning::Bin4x4 => CameraFeature::CamBin4x4mode,
+            Binning::Bin6x6 => CameraFeature::CamBin6x6mode,
+            Binning::Bin8x8 => CameraFeature::CamBin8x8mode,
+        }
+    }
+
+    fn from_factor(factor: u32) -> Option<Binning> {
+        Binning::ALL
+            .into_iter()
+            .find(|binning| binning.factor() == factor)
+    }
+}
+
+/// Gain either in the camera specific raw units or in decibels
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Gain {
+    Units(f64),
+    Decibels(f64),
+}
+
+/// Sensor characteristics at a single gain setting
 #[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SensorCurvePoint {
+    pub gain: f64,
+    /// system gain in e-/ADU
+    pub system_gain: f64,
+    /// full well capacity in e-
+    pub full_well: f64,
+    /// read noise in e-
+    pub read_noise: f64,
+}
+
+/// Sensor characteristics as a function of gain for one readout mode, sorted by gain
+#[derive(Debug, PartialEq, Clone)]
+pub struct SensorCurves {
+    pub readout_mode: u32,
+    pub points: Vec<SensorCurvePoint>,
+    /// step of `ControlGain`, gains are multiples of it counted from the first point
+    pub gain_step: f64,
+}
+
+impl SensorCurves {
+    /// Gain at which one ADU corresponds to one electron, interpolated between samples and
+    /// rounded to the gain step
+    pub fn unity_gain(&self) -> Option<f64> {
+        let gain = self.points.windows(2).find_map(|pair| {
+            let (a, b) = (pair[0], pair[1]);
+            if (a.system_gain - 1.0) * (b.system_gain - 1.0) > 0.0 {
+                return None;
+            }
+            if a.system_gain == b.system_gain {
+                return Some(a.gain);
+            }
+            Some(
+                a.gain
+                    + (1.0 - a.system_gain) * (b.gain - a.gain) / (b.system_gain - a.system_gain),
+            )
+        })?;
+        Some(round_to_step(gain, self.points[0].gain, self.gain_step))
+    }
+
+    /// Sampled gain with the lowest read noise
+    pub fn lowest_read_noise_gain(&self) -> Option<f64> {
+        self.points
+            .iter()
+            .min_by(|a, b| a.read_noise.total_cmp(&b.read_noise))
+            .map(|point| point.gain)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
 pub struct CCDChipArea {
     pub start_x: u32,
     pub start_y: u32,
@@ -222,7 +827,121 @@ pub struct CCDChipArea {
     pub height: u32,
 }
 
-#[derive(Debug, PartialEq)]
+/// Limits a sub frame has to respect, all values are in binned coordinates
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RoiConstraints {
+    max_width: u32,
+    max_height: u32,
+    effective_area: CCDChipArea,
+    alignment: u32,
+}
+
+impl RoiConstraints {
+    /// Constraints without alignment rules
+    pub fn new(max_width: u32, max_height: u32, effective_area: CCDChipArea) -> Self {
+        Self {
+            max_width,
+            max_height,
+            effective_area,
+            alignment: 1,
+        }
+    }
+
+    /// Require width and height to be multiples of `alignment`, 0 is treated as 1
+    pub fn with_alignment(self, alignment: u32) -> Self {
+        Self {
+            alignment: alignment.max(1),
+            ..self
+        }
+    }
+
+    pub fn max_width(&self) -> u32 {
+        self.max_width
+    }
+
+    pub fn max_height(&self) -> u32 {
+        self.max_height
+    }
+
+    pub fn effective_area(&self) -> CCDChipArea {
+        self.effective_area
+    }
+
+    /// width and height have to be multiples of this value
+    pub fn alignment(&self) -> u32 {
+        self.alignment
+    }
+
+    pub fn validate(&self, roi: CCDChipArea) -> Result<()> {
+        if roi.width == 0
+            || roi.height == 0
+            || roi.start_x as u64 + roi.width as u64 > self.max_width as u64
+            || roi.start_y as u64 + roi.height as u64 > self.max_height as u64
+        {
+            let error = QHYError::RoiOutOfBoundsError { roi };
+            tracing::error!(error = error.to_string().as_str());
+            return Err(eyre!(error));
+        }
+        if roi.width % self.alignment != 0 || roi.height % self.alignment != 0 {
+            let error = QHYError::RoiAlignmentError {
+                roi,
+                alignment: self.alignment,
+            };
+            tracing::error!(error = error.to_string().as_str());
+            return Err(eyre!(error));
+        }
+        let effective = self.effective_area;
+        if roi.start_x < effective.start_x
+            || roi.start_y < effective.start_y
+            || roi.start_x + roi.width > effective.start_x + effective.width
+            || roi.start_y + roi.height > effective.start_y + effective.height
+        {
+            tracing::warn!(roi = ?roi, effective_area = ?effective, "sub frame includes overscan");
+        }
+        Ok(())
+    }
+
+    /// Sub frame of the given size centered on the effective area
+    pub fn centered(&self, width: u32, height: u32) -> Result<CCDChipArea> {
+        let effective = self.effective_area;
+        self.around(
+            effective.start_x + effective.width / 2,
+            effective.start_y + effective.height / 2,
+            width,
+            height,
+        )
+    }
+
+    /// Sub frame of the given size centered on `x`, `y` and shifted to stay inside the
+    /// effective area
+    pub fn around(&self, x: u32, y: u32, width: u32, height: u32) -> Result<CCDChipArea> {
+        let width = self.align(width.min(self.effective_area.width));
+        let height = self.align(height.min(self.effective_area.height));
+        let effective = self.effective_area;
+        let start_x = x.saturating_sub(width / 2).clamp(
+            effective.start_x,
+            effective.start_x + effective.width - width,
+        );
+        let start_y = y.saturating_sub(height / 2).clamp(
+            effective.start_y,
+            effective.start_y + effective.height - height,
+        );
+        let roi = CCDChipArea {
+            start_x,
+            start_y,
+            width,
+            height,
+        };
+        self.validate(roi)?;
+        Ok(roi)
+    }
+
+    fn align(&self, value: u32) -> u32 {
+        value - value % self.alignment
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum BayerId {
     BayerGb = 1,
     BayerGr = 2,
@@ -230,14 +949,32 @@ pub enum BayerId {
     BayerRg = 4,
 }
 
-#[derive(Debug, PartialEq)]
+/// Parameters used to merge the high gain and low gain channels of dual gain sensors
+///
+/// Pixels of the high gain channel below `threshold` are scaled as
+/// `high_gain_scale * high + high_gain_offset`, all others are taken from the low gain
+/// channel as `low_gain_scale * low + low_gain_offset`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct HdrCombine {
+    pub threshold: f64,
+    pub high_gain_scale: f64,
+    pub high_gain_offset: f64,
+    pub low_gain_scale: f64,
+    pub low_gain_offset: f64,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct ReadoutMode {
     pub id: u32,
     pub name: String,
+    pub width: u32,
+    pub height: u32,
 }
 
 /// initialize the QHYCCD SDK
 ///
+/// Fails with `QHYError::UnsupportedBySdk` if the SDK is older than `MINIMUM_SDK_VERSION`.
+///
 /// # Example
 ///
 /// ```no_run
@@ -248,7 +985,17 @@ pub struct ReadoutMode {
 /// assert!(result.is_ok());
 /// ```
 pub fn init_sdk() -> Result<()> {
-    match unsafe { bindings::InitQHYCCDResource() } {
+    ensure_sdk_loaded()?;
+    let found = sdk_version()?;
+    if found < MINIMUM_SDK_VERSION {
+        let error = QHYError::UnsupportedBySdk {
+            required: MINIMUM_SDK_VERSION,
+            found,
+        };
+        tracing::error!(error = error.to_string().as_str());
+        return Err(eyre!(error));
+    }
+    match backend().init_qhyccd_resource() {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::InitSDKError { error_code };
@@ -258,8 +1005,18 @@ pub fn init_sdk() -> Result<()> {
     }
 }
 
+/// Make sure the SDK library and all required functions are loaded, only fails with the
+/// `runtime-link` feature
+fn ensure_sdk_loaded() -> Result<()> {
+    backend().load().map_err(|reason| {
+        let error = QHYError::SdkNotFound { reason };
+        tracing::error!(error = error.to_string().as_str());
+        eyre!(error)
+    })
+}
+
 pub fn release_sdk() -> Result<()> {
-    match unsafe { bindings::ReleaseQHYCCDResource() } {
+    match backend().release_qhyccd_resource() {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::CloseSDKError { error_code };
@@ -280,7 +1037,8 @@ pub fn release_sdk() -> Result<()> {
 /// assert_eq!(result, 0);
 /// ```
 pub fn scan_qhyccd() -> Result<u32> {
-    match unsafe { bindings::ScanQHYCCD() } {
+    ensure_sdk_loaded()?;
+    match backend().scan_qhyccd() {
         bindings::QHYCCD_ERROR => {
             let error = QHYError::ScanQHYCCDError;
             tracing::error!(error = error.to_string().as_str());
@@ -290,7 +1048,8 @@ pub fn scan_qhyccd() -> Result<u32> {
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// SDK version, ordered by release date
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct SDKVersion {
     pub year: u32,
     pub month: u32,
@@ -298,24 +1057,144 @@ pub struct SDKVersion {
     pub subday: u32,
 }
 
+impl fmt::Display for SDKVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}.{}",
+            self.year, self.month, self.day, self.subday
+        )
+    }
+}
+
+/// Parse versions in the `year.month.day.subday` form used by the SDK, e.g. "23.9.6.14"
+impl FromStr for SDKVersion {
+    type Err = QHYError;
+
+    fn from_str(version: &str) -> std::result::Result<Self, Self::Err> {
+        let parts = version
+            .trim()
+            .split('.')
+            .map(str::parse::<u32>)
+            .collect::<std::result::Result<Vec<_>, _>>();
+        match parts.as_deref() {
+            Ok([year, month, day, subday]) => Ok(SDKVersion {
+                year: *year,
+                month: *month,
+                day: *day,
+                subday: *subday,
+            }),
+            _ => Err(QHYError::ParseSDKVersionError {
+                version: version.to_string(),
+            }),
+        }
+    }
+}
+
+/// Oldest SDK `init_sdk` accepts, it exports every function the crate requires
+///
+/// Functions added later are checked when they are called, see `SdkFunction::introduced_in`.
+pub const MINIMUM_SDK_VERSION: SDKVersion = SDKVersion {
+    year: 21,
+    month: 2,
+    day: 1,
+    subday: 0,
+};
+
+impl SdkFunction {
+    /// First SDK exporting the function, `MINIMUM_SDK_VERSION` for functions that are older
+    pub fn introduced_in(self) -> SDKVersion {
+        let version = |year, month, day| SDKVersion {
+            year,
+            month,
+            day,
+            subday: 0,
+        };
+        match self {
+            SdkFunction::GetQHYCCDCurrentROI => version(21, 10, 12),
+            SdkFunction::SetQHYCCDTwoChannelCombineParameter => version(21, 10, 12),
+            SdkFunction::SetQHYCCDEnableLiveModeAntiRBI => version(22, 1, 24),
+            SdkFunction::QHYCCDSensorPhaseReTrain => version(22, 10, 18),
+            SdkFunction::GetQHYCCDSensorPhaseReTrain => version(22, 10, 18),
+            _ => MINIMUM_SDK_VERSION,
+        }
+    }
+}
+
+impl fmt::Display for SdkFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Version of the loaded SDK, read once by `sdk_version`
+static SDK_VERSION: Mutex<Option<SDKVersion>> = Mutex::new(None);
+
+fn sdk_version() -> Result<SDKVersion> {
+    let mut cached = SDK_VERSION.lock().unwrap_or_else(|e| e.into_inner());
+    match *cached {
+        Some(version) => Ok(version),
+        None => {
+            let version = get_sdk_version()?;
+            *cached = Some(version);
+            Ok(version)
+        }
+    }
+}
+
+/// Forget what was read from the previous backend, called when the backend changes
+fn reset_cached_state() {
+    *SDK_VERSION.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    CURRENT_BINNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+}
+
+/// Fail with `QHYError::UnsupportedBySdk` instead of calling an SDK function the loaded SDK is
+/// too old for, or with `QHYError::SdkFunctionMissing` if it does not export the function
+fn ensure_sdk_function(function: SdkFunction) -> Result<()> {
+    ensure_sdk_loaded()?;
+    let found = sdk_version()?;
+    let required = function.introduced_in();
+    let error = if found < required {
+        QHYError::UnsupportedBySdk { required, found }
+    } else if !backend().is_available(function) {
+        QHYError::SdkFunctionMissing { function, found }
+    } else {
+        return Ok(());
+    };
+    tracing::error!(
+        function = function.name(),
+        error = error.to_string().as_str()
+    );
+    Err(eyre!(error))
+}
+
+/// Whether `function` can be called, without logging an error if it can not
+fn has_sdk_function(function: SdkFunction) -> bool {
+    sdk_version().map_or(false, |found| found >= function.introduced_in())
+        && backend().is_available(function)
+}
+
 /// Get the QHYCCD SDK version
 /// # Example
 /// ```no_run
-/// # use libqhyccd_sys::get_sdk_version;
-/// # use libqhyccd_sys::SDKVersion;
+/// # use libqhyccd_sys::{get_sdk_version, MINIMUM_SDK_VERSION};
 /// use eyre::Result;
 ///
 /// let version = get_sdk_version().unwrap();
-/// assert_eq!(version, SDKVersion{year: 23, month: 9, day: 6, subday: 14});
+/// assert!(version >= MINIMUM_SDK_VERSION);
 /// ```
 /// # Errors
 /// Returns `QHYError::GetSDKVersionError` if the SDK version cannot be retrieved
 pub fn get_sdk_version() -> Result<SDKVersion> {
+    ensure_sdk_loaded()?;
     let mut year: u32 = 0;
     let mut month: u32 = 0;
     let mut day: u32 = 0;
     let mut subday: u32 = 0;
-    match unsafe { bindings::GetQHYCCDSDKVersion(&mut year, &mut month, &mut day, &mut subday) } {
+    match backend().get_qhyccd_sdk_version(&mut year, &mut month, &mut day, &mut subday) {
         bindings::QHYCCD_SUCCESS => Ok(SDKVersion {
             year,
             month,
@@ -330,47 +1209,50 @@ pub fn get_sdk_version() -> Result<SDKVersion> {
     }
 }
 
+/// Decodes a nul terminated string written by the SDK, failing if it is not UTF-8
+fn c_string_to_string(buffer: &[u8]) -> Result<String> {
+    match std::str::from_utf8(backend::c_string_bytes(buffer)) {
+        Ok(string) => Ok(string.to_string()),
+        Err(error) => {
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
+        }
+    }
+}
+
 pub fn get_camera_id(index: u32) -> Result<String> {
-    let mut id: [c_char; 32] = [0; 32];
-    unsafe {
-        match bindings::GetQHYCCDId(index, id.as_mut_ptr()) {
-            bindings::QHYCCD_SUCCESS => {
-                let id = match CStr::from_ptr(id.as_ptr()).to_str() {
-                    Ok(id) => id,
-                    Err(error) => {
-                        tracing::error!(error = error.to_string().as_str());
-                        return Err(eyre!(error));
-                    }
-                };
-                Ok(id.to_string())
-            }
-            error_code => {
-                let error = QHYError::GetCameraIdError { error_code };
-                tracing::error!(error = error.to_string().as_str());
-                Err(eyre!(error))
-            }
+    ensure_sdk_loaded()?;
+    let mut id = [0u8; 32];
+    match backend().get_qhyccd_id(index, &mut id) {
+        bindings::QHYCCD_SUCCESS => c_string_to_string(&id),
+        error_code => {
+            let error = QHYError::GetCameraIdError { error_code };
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
         }
     }
 }
 
 pub fn open_camera(id: String) -> Result<QhyccdHandle> {
-    unsafe {
-        match std::ffi::CString::new(id) {
-            Ok(id) => {
-                let handle = bindings::OpenQHYCCD(id.as_ptr());
-                Ok(QhyccdHandle::new(handle))
-            }
-            Err(error) => {
-                tracing::error!(error = error.to_string().as_str());
-                Err(eyre!(error))
-            }
+    ensure_sdk_loaded()?;
+    match std::ffi::CString::new(id.as_str()) {
+        Ok(_) => Ok(backend().open_qhyccd(&id)),
+        Err(error) => {
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
         }
     }
 }
 
 pub fn close_camera(handle: QhyccdHandle) -> Result<()> {
-    match unsafe { bindings::CloseQHYCCD(handle.ptr) } {
-        bindings::QHYCCD_SUCCESS => Ok(()),
+    match backend().close_qhyccd(handle) {
+        bindings::QHYCCD_SUCCESS => {
+            CURRENT_BINNING
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .retain(|(ptr, _)| *ptr != handle.ptr as usize);
+            Ok(())
+        }
         error_code => {
             let error = QHYError::CloseCameraError { error_code };
             tracing::error!(error = error.to_string().as_str());
@@ -381,7 +1263,7 @@ pub fn close_camera(handle: QhyccdHandle) -> Result<()> {
 
 pub fn get_firmware_version(handle: QhyccdHandle) -> Result<String> {
     let mut version = [0u8; 32];
-    match unsafe { bindings::GetQHYCCDFWVersion(handle.ptr, version.as_mut_ptr()) } {
+    match backend().get_qhyccd_fw_version(handle, &mut version) {
         bindings::QHYCCD_SUCCESS => {
             if version[0] >> 4 <= 9 {
                 Ok(format!(
@@ -408,7 +1290,7 @@ pub fn get_firmware_version(handle: QhyccdHandle) -> Result<String> {
 }
 
 pub fn is_feature_supported(handle: QhyccdHandle, feature: CameraFeature) -> Result<u32> {
-    match unsafe { bindings::IsQHYCCDControlAvailable(handle.ptr, feature as u32) } {
+    match backend().is_qhyccd_control_available(handle, feature) {
         bindings::QHYCCD_ERROR => {
             let error = QHYError::IsFeatureSupportedError { feature };
             tracing::error!(error = error.to_string().as_str());
@@ -419,7 +1301,7 @@ pub fn is_feature_supported(handle: QhyccdHandle, feature: CameraFeature) -> Res
 }
 
 pub fn set_readout_mode(handle: QhyccdHandle, mode: u32) -> Result<()> {
-    match unsafe { bindings::SetQHYCCDReadMode(handle.ptr, mode) } {
+    match backend().set_qhyccd_read_mode(handle, mode) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::SetReadoutModeError { error_code };
@@ -430,7 +1312,7 @@ pub fn set_readout_mode(handle: QhyccdHandle, mode: u32) -> Result<()> {
 }
 
 pub fn set_stream_mode(handle: QhyccdHandle, mode: CameraStreamMode) -> Result<()> {
-    match unsafe { bindings::SetQHYCCDStreamMode(handle.ptr, mode as u8) } {
+    match backend().set_qhyccd_stream_mode(handle, mode as u8) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::SetStreamModeError { error_code };
@@ -441,7 +1323,7 @@ pub fn set_stream_mode(handle: QhyccdHandle, mode: CameraStreamMode) -> Result<(
 }
 
 pub fn init_camera(handle: QhyccdHandle) -> Result<()> {
-    match unsafe { bindings::InitQHYCCD(handle.ptr) } {
+    match backend().init_qhyccd(handle) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::InitCameraError { error_code };
@@ -452,34 +1334,9 @@ pub fn init_camera(handle: QhyccdHandle) -> Result<()> {
 }
 
 pub fn get_ccd_info(handle: QhyccdHandle) -> Result<CCDChipInfo> {
-    let mut chipw: f64 = 0.0;
-    let mut chiph: f64 = 0.0;
-    let mut imagew: u32 = 0;
-    let mut imageh: u32 = 0;
-    let mut pixelw: f64 = 0.0;
-    let mut pixelh: f64 = 0.0;
-    let mut bpp: u32 = 0;
-    match unsafe {
-        bindings::GetQHYCCDChipInfo(
-            handle.ptr,
-            &mut chipw as *mut f64,
-            &mut chiph as *mut f64,
-            &mut imagew as *mut u32,
-            &mut imageh as *mut u32,
-            &mut pixelw as *mut f64,
-            &mut pixelh as *mut f64,
-            &mut bpp as *mut u32,
-        )
-    } {
-        bindings::QHYCCD_SUCCESS => Ok(CCDChipInfo {
-            chip_width: chipw,
-            chip_height: chiph,
-            image_width: imagew,
-            image_height: imageh,
-            pixel_width: pixelw,
-            pixel_height: pixelh,
-            bits_per_pixel: bpp,
-        }),
+    let mut info = CCDChipInfo::default();
+    match backend().get_qhyccd_chip_info(handle, &mut info) {
+        bindings::QHYCCD_SUCCESS => Ok(info),
         error_code => {
             let error = QHYError::GetCCDInfoError { error_code };
             tracing::error!(error = error.to_string().as_str());
@@ -489,7 +1346,7 @@ pub fn get_ccd_info(handle: QhyccdHandle) -> Result<CCDChipInfo> {
 }
 
 pub fn set_bit_mode(handle: QhyccdHandle, mode: u32) -> Result<()> {
-    match unsafe { bindings::SetQHYCCDBitsMode(handle.ptr, mode) } {
+    match backend().set_qhyccd_bits_mode(handle, mode) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::SetBitModeError { error_code };
@@ -499,8 +1356,42 @@ pub fn set_bit_mode(handle: QhyccdHandle, mode: u32) -> Result<()> {
     }
 }
 
+/// Set the bit depth of both the sensor readout and the transfer so the buffer layout matches
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{open_camera, set_bit_depth, BitDepth};
+/// let camera = open_camera("QHY178M-222b16468c5966524".to_string()).unwrap();
+/// set_bit_depth(camera, BitDepth::Sixteen).unwrap();
+/// ```
+pub fn set_bit_depth(handle: QhyccdHandle, bit_depth: BitDepth) -> Result<()> {
+    if is_feature_supported(handle, bit_depth.feature()).is_err() {
+        let error = QHYError::UnsupportedBitDepthError { bit_depth };
+        tracing::error!(error = error.to_string().as_str());
+        return Err(eyre!(error));
+    }
+    set_bit_mode(handle, bit_depth.bits())?;
+    if is_feature_supported(handle, CameraFeature::ControlTransferBit).is_ok() {
+        set_parameter(
+            handle,
+            CameraFeature::ControlTransferBit,
+            bit_depth.bits() as f64,
+        )?;
+    }
+    Ok(())
+}
+
+/// Get the bit depths the camera supports
+pub fn supported_bit_depths(handle: QhyccdHandle) -> Vec<BitDepth> {
+    [BitDepth::Eight, BitDepth::Sixteen, BitDepth::ThirtyTwo]
+        .into_iter()
+        .filter(|bit_depth| is_feature_supported(handle, bit_depth.feature()).is_ok())
+        .collect()
+}
+
 pub fn set_debayer(handle: QhyccdHandle, on: bool) -> Result<()> {
-    match unsafe { bindings::SetQHYCCDDebayerOnOff(handle.ptr, on) } {
+    match backend().set_qhyccd_debayer_on_off(handle, on) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::SetDebayerError { error_code };
@@ -510,9 +1401,93 @@ pub fn set_debayer(handle: QhyccdHandle, on: bool) -> Result<()> {
     }
 }
 
+/// Horizontal and vertical bin factors last set per camera, the SDK offers no way to read them
+/// back
+static CURRENT_BINNING: Mutex<Vec<(usize, (u32, u32))>> = Mutex::new(Vec::new());
+
+fn bin_factors(handle: QhyccdHandle) -> (u32, u32) {
+    CURRENT_BINNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|(ptr, _)| *ptr == handle.ptr as usize)
+        .map_or((1, 1), |&(_, (bin_x, bin_y))| (bin_x.max(1), bin_y.max(1)))
+}
+
+/// Get the binning last set through `set_bin_mode` or `set_binning`, 1x1 if none or a bin mode
+/// other than the square binnings was set
+pub fn get_binning(handle: QhyccdHandle) -> Binning {
+    let (bin_x, bin_y) = bin_factors(handle);
+    Binning::from_factor(bin_x)
+        .filter(|_| bin_x == bin_y)
+        .unwrap_or(Binning::Bin1x1)
+}
+
+/// Get the binning modes the camera supports
+pub fn supported_binnings(handle: QhyccdHandle) -> Vec<Binning> {
+    Binning::ALL
+        .into_iter()
+        .filter(|binning| is_feature_supported(handle, binning.feature()).is_ok())
+        .collect()
+}
+
+/// Set a binning supported by the camera and rescale the current sub frame to it
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{open_camera, set_binning, Binning};
+/// let camera = open_camera("QHY178M-222b16468c5966524".to_string()).unwrap();
+/// set_binning(camera, Binning::Bin2x2).unwrap();
+/// ```
+pub fn set_binning(handle: QhyccdHandle, binning: Binning) -> Result<()> {
+    if is_feature_supported(handle, binning.feature()).is_err() {
+        let error = QHYError::UnsupportedBinningError { binning };
+        tracing::error!(error = error.to_string().as_str());
+        return Err(eyre!(error));
+    }
+    let (previous_x, previous_y) = bin_factors(handle);
+    let roi = match has_sdk_function(SdkFunction::GetQHYCCDCurrentROI) {
+        true => get_roi(handle)?,
+        // SDKs without GetQHYCCDCurrentROI, assume the effective area is used
+        false => {
+            let effective = get_effective_area(handle)?;
+            CCDChipArea {
+                start_x: effective.start_x / previous_x,
+                start_y: effective.start_y / previous_y,
+                width: effective.width / previous_x,
+                height: effective.height / previous_y,
+            }
+        }
+    };
+    set_bin_mode(handle, binning.factor(), binning.factor())?;
+    let scale = |value: u32, previous: u32| {
+        (value as u64 * previous as u64 / binning.factor() as u64) as u32
+    };
+    let rescaled = CCDChipArea {
+        start_x: scale(roi.start_x, previous_x),
+        start_y: scale(roi.start_y, previous_y),
+        width: scale(roi.width, previous_x),
+        height: scale(roi.height, previous_y),
+    };
+    if let Err(error) = set_roi(handle, rescaled) {
+        // leave the camera as it was instead of binned with the old sub frame
+        if set_bin_mode(handle, previous_x, previous_y).is_ok() {
+            let _ = set_roi(handle, roi);
+        }
+        return Err(error);
+    }
+    Ok(())
+}
+
 pub fn set_bin_mode(handle: QhyccdHandle, bin_x: u32, bin_y: u32) -> Result<()> {
-    match unsafe { bindings::SetQHYCCDBinMode(handle.ptr, bin_x, bin_y) } {
-        bindings::QHYCCD_SUCCESS => Ok(()),
+    match backend().set_qhyccd_bin_mode(handle, bin_x, bin_y) {
+        bindings::QHYCCD_SUCCESS => {
+            let mut current = CURRENT_BINNING.lock().unwrap_or_else(|e| e.into_inner());
+            current.retain(|(ptr, _)| *ptr != handle.ptr as usize);
+            current.push((handle.ptr as usize, (bin_x, bin_y)));
+            Ok(())
+        }
         error_code => {
             let error = QHYError::SetBinModeError { error_code };
             tracing::error!(error = error.to_string().as_str());
@@ -522,9 +1497,7 @@ pub fn set_bin_mode(handle: QhyccdHandle, bin_x: u32, bin_y: u32) -> Result<()>
 }
 
 pub fn set_roi(handle: QhyccdHandle, roi: CCDChipArea) -> Result<()> {
-    match unsafe {
-        bindings::SetQHYCCDResolution(handle.ptr, roi.start_x, roi.start_y, roi.width, roi.height)
-    } {
+    match backend().set_qhyccd_resolution(handle, roi) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::SetRoiError { error_code };
@@ -534,9 +1507,86 @@ pub fn set_roi(handle: QhyccdHandle, roi: CCDChipArea) -> Result<()> {
     }
 }
 
-pub fn set_parameter(handle: QhyccdHandle, feature: CameraFeature, value: f64) -> Result<()> {
-    match unsafe { bindings::SetQHYCCDParam(handle.ptr, feature as u32, value) } {
-        bindings::QHYCCD_SUCCESS => Ok(()),
+/// Sub frame size alignment by model prefix, models not listed accept any size
+const ROI_ALIGNMENTS: [(&str, u32); 8] = [
+    ("QHY5III", 4),
+    ("QHY174", 4),
+    ("QHY178", 4),
+    ("QHY183", 4),
+    ("QHY268", 8),
+    ("QHY294", 8),
+    ("QHY600", 8),
+    ("QHY411", 8),
+];
+
+/// Get the sub frame size alignment the camera model needs, in binned pixels
+pub fn roi_alignment(model: &str) -> u32 {
+    ROI_ALIGNMENTS
+        .iter()
+        .find(|(prefix, _)| model.starts_with(prefix))
+        .map_or(1, |(_, alignment)| *alignment)
+}
+
+/// Get the sub frame limits for the current readout mode and binning
+///
+/// The alignment is derived from the camera model with `roi_alignment`, use
+/// `RoiConstraints::with_alignment` to override it.
+pub fn get_roi_constraints(handle: QhyccdHandle) -> Result<RoiConstraints> {
+    let info = get_ccd_info(handle)?;
+    let mode = get_readout_mode(handle)?;
+    let effective = get_effective_area(handle)?;
+    let (bin_x, bin_y) = bin_factors(handle);
+    let mut width = info.image_width;
+    let mut height = info.image_height;
+    if mode.width > 0 && mode.height > 0 {
+        width = width.min(mode.width);
+        height = height.min(mode.height);
+    }
+    let effective = CCDChipArea {
+        start_x: effective.start_x / bin_x,
+        start_y: effective.start_y / bin_y,
+        width: effective.width / bin_x,
+        height: effective.height / bin_y,
+    };
+    Ok(
+        RoiConstraints::new(width / bin_x, height / bin_y, effective)
+            .with_alignment(roi_alignment(&get_model(handle)?)),
+    )
+}
+
+/// Validate the sub frame against the camera geometry before setting it
+pub fn set_roi_checked(handle: QhyccdHandle, roi: CCDChipArea) -> Result<()> {
+    get_roi_constraints(handle)?.validate(roi)?;
+    set_roi(handle, roi)
+}
+
+/// Sub frame of the given size centered on the sensor, e.g. for planetary imaging
+pub fn centered_roi(handle: QhyccdHandle, width: u32, height: u32) -> Result<CCDChipArea> {
+    get_roi_constraints(handle)?.centered(width, height)
+}
+
+/// Square sub frame of `size` centered on `x`, `y`, e.g. around a guide star
+pub fn roi_around(handle: QhyccdHandle, x: u32, y: u32, size: u32) -> Result<CCDChipArea> {
+    get_roi_constraints(handle)?.around(x, y, size, size)
+}
+
+/// Get the current sub frame in binned coordinates
+pub fn get_roi(handle: QhyccdHandle) -> Result<CCDChipArea> {
+    ensure_sdk_function(SdkFunction::GetQHYCCDCurrentROI)?;
+    let mut area = CCDChipArea::default();
+    match backend().get_qhyccd_current_roi(handle, &mut area) {
+        bindings::QHYCCD_SUCCESS => Ok(area),
+        error_code => {
+            let error = QHYError::GetRoiError { error_code };
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
+        }
+    }
+}
+
+pub fn set_parameter(handle: QhyccdHandle, feature: CameraFeature, value: f64) -> Result<()> {
+    match backend().set_qhyccd_param(handle, feature, value) {
+        bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::SetParameterError { error_code };
             tracing::error!(error = error.to_string().as_str());
@@ -545,8 +1595,500 @@ pub fn set_parameter(handle: QhyccdHandle, feature: CameraFeature, value: f64) -
     }
 }
 
+pub fn get_parameter(handle: QhyccdHandle, feature: CameraFeature) -> Result<f64> {
+    let value = backend().get_qhyccd_param(handle, feature);
+    if value == bindings::QHYCCD_ERROR as f64 {
+        let error = QHYError::GetParameterError { feature };
+        tracing::error!(error = error.to_string().as_str());
+        return Err(eyre!(error));
+    }
+    Ok(value)
+}
+
+/// Set the exposure time, checked against the exposure range of the camera
+///
+/// The SDK takes exposure times in microseconds, the requested time is rounded to the exposure
+/// step of the camera and a warning is emitted when that changes it.
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{open_camera, set_exposure};
+/// # use std::time::Duration;
+/// let camera = open_camera("QHY178M-222b16468c5966524".to_string()).unwrap();
+/// set_exposure(camera, Duration::from_millis(2)).unwrap();
+/// ```
+pub fn set_exposure(handle: QhyccdHandle, exposure: Duration) -> Result<()> {
+    let (min, max, step) = checked_min_max_step(handle, CameraFeature::ControlExposure)?;
+    let micros = exposure.as_secs_f64() * 1_000_000.0;
+    if micros < min || micros > max {
+        let error = QHYError::ExposureOutOfRangeError {
+            exposure,
+            min: micros_to_duration(min),
+            max: micros_to_duration(max),
+        };
+        tracing::error!(error = error.to_string().as_str());
+        return Err(eyre!(error));
+    }
+    let rounded = match step > 0.0 {
+        true => round_to_step(micros, min, step).clamp(min, max),
+        false => micros.round(),
+    };
+    if rounded != micros {
+        tracing::warn!(
+            exposure = ?exposure,
+            exposure_us = rounded,
+            "exposure rounded to the exposure step of the camera"
+        );
+    }
+    set_parameter(handle, CameraFeature::ControlExposure, rounded)
+}
+
+/// Get the exposure time
+pub fn exposure(handle: QhyccdHandle) -> Result<Duration> {
+    let micros = get_parameter(handle, CameraFeature::ControlExposure)?;
+    Ok(micros_to_duration(micros))
+}
+
+/// Convert microseconds reported by the SDK, saturating instead of panicking on values a
+/// `Duration` can not hold
+fn micros_to_duration(micros: f64) -> Duration {
+    let secs = micros / 1_000_000.0;
+    if secs.is_nan() || secs <= 0.0 {
+        Duration::ZERO
+    } else if secs >= u64::MAX as f64 {
+        Duration::MAX
+    } else {
+        Duration::from_secs_f64(secs)
+    }
+}
+
+/// Round `value` to the nearest multiple of `step` counted from `min`, unchanged without a step
+fn round_to_step(value: f64, min: f64, step: f64) -> f64 {
+    match step > 0.0 {
+        true => min + ((value - min) / step).round() * step,
+        false => value,
+    }
+}
+
+/// Get the minimum, maximum and step size of a parameter
+pub fn get_parameter_min_max_step(
+    handle: QhyccdHandle,
+    feature: CameraFeature,
+) -> Result<(f64, f64, f64)> {
+    let mut min: f64 = 0.0;
+    let mut max: f64 = 0.0;
+    let mut step: f64 = 0.0;
+    match backend().get_qhyccd_param_min_max_step(handle, feature, &mut min, &mut max, &mut step) {
+        bindings::QHYCCD_SUCCESS => Ok((min, max, step)),
+        _ => {
+            let error = QHYError::GetParameterMinMaxStepError { feature };
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
+        }
+    }
+}
+
+/// Minimum, maximum and step size of a parameter, failing if the minimum and maximum can not be
+/// used as a range
+fn checked_min_max_step(handle: QhyccdHandle, feature: CameraFeature) -> Result<(f64, f64, f64)> {
+    let (min, max, step) = get_parameter_min_max_step(handle, feature)?;
+    if min.is_nan() || max.is_nan() || min > max {
+        let error = QHYError::InvalidParameterRangeError { feature, min, max };
+        tracing::error!(error = error.to_string().as_str());
+        return Err(eyre!(error));
+    }
+    Ok((min, max, step))
+}
+
+pub fn set_ddr_enabled(handle: QhyccdHandle, on: bool) -> Result<()> {
+    set_parameter(
+        handle,
+        CameraFeature::ControlDDR,
+        if on { 1.0 } else { 0.0 },
+    )
+}
+
+/// Get the capacity and current fill level of the onboard DDR buffer
+///
+/// The SDK reports the fill level as the value of `DDRBufferCapacity` and has no separate query
+/// for the buffer size, the maximum of that control is the largest fill level the camera can
+/// report and is used as the capacity.
+pub fn get_ddr_buffer(handle: QhyccdHandle) -> Result<DdrBuffer> {
+    let (_, capacity, _) = get_parameter_min_max_step(handle, CameraFeature::DDRBufferCapacity)?;
+    let fill = get_parameter(handle, CameraFeature::DDRBufferCapacity)?;
+    Ok(DdrBuffer { capacity, fill })
+}
+
+pub fn get_ddr_read_threshold(handle: QhyccdHandle) -> Result<f64> {
+    get_parameter(handle, CameraFeature::DDRBufferReadThreshold)
+}
+
+/// Set how full the DDR buffer has to be before the camera starts sending frames
+pub fn set_ddr_read_threshold(handle: QhyccdHandle, threshold: f64) -> Result<()> {
+    set_parameter(handle, CameraFeature::DDRBufferReadThreshold, threshold)
+}
+
+pub fn set_vacuum_pump(handle: QhyccdHandle, on: bool) -> Result<()> {
+    set_parameter(
+        handle,
+        CameraFeature::ControlVacuumPump,
+        if on { 1.0 } else { 0.0 },
+    )
+}
+
+pub fn is_vacuum_pump_on(handle: QhyccdHandle) -> Result<bool> {
+    Ok(get_parameter(handle, CameraFeature::ControlVacuumPump)? != 0.0)
+}
+
+pub fn set_cycle_pump(handle: QhyccdHandle, on: bool) -> Result<()> {
+    set_parameter(
+        handle,
+        CameraFeature::ControlSensorChamberCyclePump,
+        if on { 1.0 } else { 0.0 },
+    )
+}
+
+pub fn is_cycle_pump_on(handle: QhyccdHandle) -> Result<bool> {
+    Ok(get_parameter(handle, CameraFeature::ControlSensorChamberCyclePump)? != 0.0)
+}
+
+/// Get the relative humidity inside the sensor chamber in percent
+pub fn get_humidity(handle: QhyccdHandle) -> Result<f64> {
+    ensure_sdk_function(SdkFunction::GetQHYCCDHumidity)?;
+    let mut humidity: f64 = 0.0;
+    match backend().get_qhyccd_humidity(handle, &mut humidity) {
+        bindings::QHYCCD_SUCCESS => Ok(humidity),
+        error_code => {
+            let error = QHYError::GetHumidityError { error_code };
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
+        }
+    }
+}
+
+/// Start retraining the phase of the sensor data lines
+pub fn sensor_phase_retrain(handle: QhyccdHandle) -> Result<()> {
+    ensure_sdk_function(SdkFunction::QHYCCDSensorPhaseReTrain)?;
+    match backend().qhyccd_sensor_phase_retrain(handle) {
+        bindings::QHYCCD_SUCCESS => Ok(()),
+        error_code => {
+            let error = QHYError::SensorPhaseReTrainError { error_code };
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
+        }
+    }
+}
+
+pub fn is_sensor_phase_retrain_pending(handle: QhyccdHandle) -> Result<bool> {
+    ensure_sdk_function(SdkFunction::GetQHYCCDSensorPhaseReTrain)?;
+    match backend().get_qhyccd_sensor_phase_retrain(handle) {
+        bindings::QHYCCD_ERROR => {
+            let error = QHYError::GetSensorPhaseReTrainError;
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
+        }
+        status => Ok(status != 0),
+    }
+}
+
+/// Read the UVLO, phase retrain and FPGA watchdog statuses of the sensor
+pub fn get_sensor_health(handle: QhyccdHandle) -> Result<SensorHealth> {
+    let ulvo = match is_feature_supported(handle, CameraFeature::CamSensorUlvoStatus) {
+        Ok(_) => {
+            UlvoStatus::from(get_parameter(handle, CameraFeature::CamSensorUlvoStatus)? as u32)
+        }
+        Err(_) => UlvoStatus::Normal,
+    };
+    let phase_retrain_pending =
+        match is_feature_supported(handle, CameraFeature::CamSensorPhaseReTrain) {
+            // SDKs too old for the retrain status still report the other statuses
+            Ok(_) if has_sdk_function(SdkFunction::GetQHYCCDSensorPhaseReTrain) => {
+                is_sensor_phase_retrain_pending(handle)?
+            }
+            _ => false,
+        };
+    let fpga_watchdog_triggered = match is_feature_supported(handle, CameraFeature::CamWatchDogFpga)
+    {
+        Ok(_) => get_parameter(handle, CameraFeature::CamWatchDogFpga)? != 0.0,
+        Err(_) => false,
+    };
+    Ok(SensorHealth {
+        ulvo,
+        phase_retrain_pending,
+        fpga_watchdog_triggered,
+    })
+}
+
+/// Let the camera pre-flash the sensor before each exposure to remove residual bulk image
+pub fn set_remove_rbi(handle: QhyccdHandle, on: bool) -> Result<()> {
+    set_parameter(
+        handle,
+        CameraFeature::ControlRemoveRbi,
+        if on { 1.0 } else { 0.0 },
+    )
+}
+
+/// Switch between rolling shutter and global reset readout
+pub fn set_global_reset(handle: QhyccdHandle, on: bool) -> Result<()> {
+    set_parameter(
+        handle,
+        CameraFeature::ControlGlobalReset,
+        if on { 1.0 } else { 0.0 },
+    )
+}
+
+/// Enable residual bulk image mitigation in live mode
+pub fn set_live_mode_anti_rbi(handle: QhyccdHandle, on: bool) -> Result<()> {
+    ensure_sdk_function(SdkFunction::SetQHYCCDEnableLiveModeAntiRBI)?;
+    match backend().set_qhyccd_enable_live_mode_anti_rbi(handle, on as u32) {
+        bindings::QHYCCD_SUCCESS => Ok(()),
+        error_code => {
+            let error = QHYError::SetLiveModeAntiRbiError { error_code };
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
+        }
+    }
+}
+
+/// Set a parameter after checking it against the range reported by the camera
+pub fn set_parameter_checked(
+    handle: QhyccdHandle,
+    feature: CameraFeature,
+    value: f64,
+) -> Result<()> {
+    let (min, max, _) = get_parameter_min_max_step(handle, feature)?;
+    if value < min || value > max {
+        let error = QHYError::ParameterOutOfRangeError {
+            feature,
+            value,
+            min,
+            max,
+        };
+        tracing::error!(error = error.to_string().as_str());
+        return Err(eyre!(error));
+    }
+    set_parameter(handle, feature, value)
+}
+
+pub fn get_white_balance(handle: QhyccdHandle) -> Result<WhiteBalance> {
+    Ok(WhiteBalance {
+        red: get_parameter(handle, CameraFeature::ControlWbr)?,
+        green: get_parameter(handle, CameraFeature::ControlWbg)?,
+        blue: get_parameter(handle, CameraFeature::ControlWbb)?,
+    })
+}
+
+/// Set the white balance gains, each gain is checked against the range of the camera
+pub fn set_white_balance(handle: QhyccdHandle, white_balance: WhiteBalance) -> Result<()> {
+    set_parameter_checked(handle, CameraFeature::ControlWbr, white_balance.red)?;
+    set_parameter_checked(handle, CameraFeature::ControlWbg, white_balance.green)?;
+    set_parameter_checked(handle, CameraFeature::ControlWbb, white_balance.blue)
+}
+
+pub fn set_auto_white_balance(handle: QhyccdHandle, on: bool) -> Result<()> {
+    set_parameter(
+        handle,
+        CameraFeature::ControlAutowhitebalance,
+        if on { 1.0 } else { 0.0 },
+    )
+}
+
+/// Compute the white balance from a debayered frame assuming a grey world and apply it
+///
+/// The image is expected in the BGR channel order delivered by the SDK when debayering is on.
+/// The red and blue gains are scaled so their channel means match the green channel, the
+/// result is clamped to the range of the camera and returned.
+pub fn white_balance_from_image(handle: QhyccdHandle, image: &ImageData) -> Result<WhiteBalance> {
+    let bytes_per_sample = match image.bits_per_pixel {
+        8 => 1,
+        16 => 2,
+        _ => 0,
+    };
+    if image.channels != 3 || bytes_per_sample == 0 || image.data.is_empty() {
+        let error = QHYError::WhiteBalanceImageError;
+        tracing::error!(error = error.to_string().as_str());
+        return Err(eyre!(error));
+    }
+    let mut sums = [0f64; 3];
+    for pixel in image.data.chunks_exact(3 * bytes_per_sample) {
+        for (channel, sample) in pixel.chunks_exact(bytes_per_sample).enumerate() {
+            sums[channel] += match bytes_per_sample {
+                1 => sample[0] as f64,
+                _ => u16::from_le_bytes([sample[0], sample[1]]) as f64,
+            };
+        }
+    }
+    let [blue_sum, green_sum, red_sum] = sums;
+    if blue_sum == 0.0 || red_sum == 0.0 {
+        let error = QHYError::WhiteBalanceImageError;
+        tracing::error!(error = error.to_string().as_str());
+        return Err(eyre!(error));
+    }
+    let current = get_white_balance(handle)?;
+    let (red_min, red_max, _) = checked_min_max_step(handle, CameraFeature::ControlWbr)?;
+    let (blue_min, blue_max, _) = checked_min_max_step(handle, CameraFeature::ControlWbb)?;
+    let white_balance = WhiteBalance {
+        red: (current.red * green_sum / red_sum).clamp(red_min, red_max),
+        green: current.green,
+        blue: (current.blue * green_sum / blue_sum).clamp(blue_min, blue_max),
+    };
+    tracing::debug!(white_balance = ?white_balance);
+    set_white_balance(handle, white_balance)?;
+    Ok(white_balance)
+}
+
+/// Set the gain
+///
+/// Gains in dB are converted by the camera if it supports `CameraFeature::CamGainDbConversion`,
+/// otherwise they are mapped linearly from the `ControlGaindB` range onto the `ControlGain`
+/// range and rounded to its step.
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{open_camera, set_gain, Gain};
+/// let camera = open_camera("QHY268M-c3b5a9e1d7f20468".to_string()).unwrap();
+/// set_gain(camera, Gain::Decibels(12.0)).unwrap();
+/// ```
+pub fn set_gain(handle: QhyccdHandle, gain: Gain) -> Result<()> {
+    match gain {
+        Gain::Units(units) => set_parameter_checked(handle, CameraFeature::ControlGain, units),
+        Gain::Decibels(db) => {
+            match is_feature_supported(handle, CameraFeature::CamGainDbConversion) {
+                Ok(_) => set_parameter_checked(handle, CameraFeature::ControlGaindB, db),
+                Err(_) => {
+                    let ranges = gain_ranges(handle)?;
+                    let units = ranges.units_min
+                        + (db - ranges.db_min) / (ranges.db_max - ranges.db_min)
+                            * (ranges.units_max - ranges.units_min);
+                    let units = round_to_step(units, ranges.units_min, ranges.units_step);
+                    tracing::debug!(gain_db = db, gain_units = units);
+                    set_parameter_checked(handle, CameraFeature::ControlGain, units)
+                }
+            }
+        }
+    }
+}
+
+/// Get the gain in raw units
+pub fn get_gain(handle: QhyccdHandle) -> Result<Gain> {
+    Ok(Gain::Units(get_parameter(
+        handle,
+        CameraFeature::ControlGain,
+    )?))
+}
+
+/// Get the gain in dB, converted the same way as in `set_gain`
+pub fn get_gain_db(handle: QhyccdHandle) -> Result<Gain> {
+    if is_feature_supported(handle, CameraFeature::CamGainDbConversion).is_ok() {
+        return Ok(Gain::Decibels(get_parameter(
+            handle,
+            CameraFeature::ControlGaindB,
+        )?));
+    }
+    let ranges = gain_ranges(handle)?;
+    let units = get_parameter(handle, CameraFeature::ControlGain)?;
+    Ok(Gain::Decibels(
+        ranges.db_min
+            + (units - ranges.units_min) / (ranges.units_max - ranges.units_min)
+                * (ranges.db_max - ranges.db_min),
+    ))
+}
+
+struct GainRanges {
+    units_min: f64,
+    units_max: f64,
+    units_step: f64,
+    db_min: f64,
+    db_max: f64,
+}
+
+/// Ranges of `ControlGain` and `ControlGaindB` for converting between them on cameras without
+/// `CameraFeature::CamGainDbConversion`
+fn gain_ranges(handle: QhyccdHandle) -> Result<GainRanges> {
+    let (units_min, units_max, units_step) =
+        get_parameter_min_max_step(handle, CameraFeature::ControlGain)?;
+    let ranges = match get_parameter_min_max_step(handle, CameraFeature::ControlGaindB) {
+        Ok((db_min, db_max, _)) => Some(GainRanges {
+            units_min,
+            units_max,
+            units_step,
+            db_min,
+            db_max,
+        }),
+        Err(_) => None,
+    };
+    match ranges {
+        Some(ranges) if ranges.units_max > ranges.units_min && ranges.db_max > ranges.db_min => {
+            Ok(ranges)
+        }
+        _ => {
+            let error = QHYError::GainDbUnsupportedError;
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
+        }
+    }
+}
+
+/// Sample system gain, full well and read noise over the gain range of a readout mode
+///
+/// The gain range is sampled at `samples` evenly spaced points. The camera's readout mode and
+/// gain are restored afterwards.
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{get_sensor_curves, open_camera};
+/// let camera = open_camera("QHY268M-c3b5a9e1d7f20468".to_string()).unwrap();
+/// let curves = get_sensor_curves(camera, 1, 32).unwrap();
+/// println!("unity gain {:?}", curves.unity_gain());
+/// ```
+pub fn get_sensor_curves(
+    handle: QhyccdHandle,
+    readout_mode: u32,
+    samples: u32,
+) -> Result<SensorCurves> {
+    let previous_mode = get_readout_mode(handle)?.id;
+    let previous_gain = get_parameter(handle, CameraFeature::ControlGain)?;
+    if previous_mode != readout_mode {
+        set_readout_mode(handle, readout_mode)?;
+    }
+    let (min, max, step) = checked_min_max_step(handle, CameraFeature::ControlGain)?;
+    let samples = samples.max(2);
+    let mut gains = (0..samples)
+        .map(|sample| {
+            let gain = min + (max - min) * sample as f64 / (samples - 1) as f64;
+            round_to_step(gain, min, step).min(max)
+        })
+        .collect::<Vec<_>>();
+    // more samples than gain steps give the same gain more than once
+    gains.dedup();
+    let points = gains
+        .into_iter()
+        .map(|gain| {
+            set_parameter(handle, CameraFeature::ControlGain, gain)?;
+            Ok(SensorCurvePoint {
+                gain,
+                system_gain: get_parameter(handle, CameraFeature::CamCurveSystemGain)?,
+                full_well: get_parameter(handle, CameraFeature::CamCurveFullWell)?,
+                read_noise: get_parameter(handle, CameraFeature::CamCurveReadoutNoise)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>();
+    if previous_mode != readout_mode {
+        set_readout_mode(handle, previous_mode)?;
+    }
+    set_parameter(handle, CameraFeature::ControlGain, previous_gain)?;
+    Ok(SensorCurves {
+        readout_mode,
+        points: points?,
+        gain_step: step,
+    })
+}
+
 pub fn begin_live(handle: QhyccdHandle) -> Result<()> {
-    match unsafe { bindings::BeginQHYCCDLive(handle.ptr) } {
+    match backend().begin_qhyccd_live(handle) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::BeginLiveError { error_code };
@@ -557,7 +2099,7 @@ pub fn begin_live(handle: QhyccdHandle) -> Result<()> {
 }
 
 pub fn end_live(handle: QhyccdHandle) -> Result<()> {
-    match unsafe { bindings::StopQHYCCDLive(handle.ptr) } {
+    match backend().stop_qhyccd_live(handle) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::EndLiveError { error_code };
@@ -568,7 +2110,7 @@ pub fn end_live(handle: QhyccdHandle) -> Result<()> {
 }
 
 pub fn get_image_size(handle: QhyccdHandle) -> Result<usize> {
-    match unsafe { bindings::GetQHYCCDMemLength(handle.ptr) } {
+    match backend().get_qhyccd_mem_length(handle) {
         bindings::QHYCCD_ERROR => {
             let error = QHYError::GetImageSizeError;
             tracing::error!(error = error.to_string().as_str());
@@ -579,28 +2121,12 @@ pub fn get_image_size(handle: QhyccdHandle) -> Result<usize> {
 }
 
 pub fn get_live_frame(handle: QhyccdHandle, buffer_size: usize) -> Result<ImageData> {
-    let mut width: u32 = 0;
-    let mut height: u32 = 0;
-    let mut bpp: u32 = 0;
-    let mut channels: u32 = 0;
-    let mut buffer = vec![0u8; buffer_size];
-    match unsafe {
-        bindings::GetQHYCCDLiveFrame(
-            handle.ptr,
-            &mut width as *mut u32,
-            &mut height as *mut u32,
-            &mut bpp as *mut u32,
-            &mut channels as *mut u32,
-            buffer.as_mut_ptr(),
-        )
-    } {
-        bindings::QHYCCD_SUCCESS => Ok(ImageData {
-            data: buffer,
-            width,
-            height,
-            bits_per_pixel: bpp,
-            channels,
-        }),
+    let mut image = ImageData {
+        data: vec![0u8; buffer_size],
+        ..Default::default()
+    };
+    match backend().get_qhyccd_live_frame(handle, &mut image) {
+        bindings::QHYCCD_SUCCESS => Ok(image),
         error_code => {
             let error = QHYError::GetLiveFrameError { error_code };
             tracing::error!(error = error.to_string().as_str());
@@ -610,28 +2136,12 @@ pub fn get_live_frame(handle: QhyccdHandle, buffer_size: usize) -> Result<ImageD
 }
 
 pub fn get_single_frame(handle: QhyccdHandle, buffer_size: usize) -> Result<ImageData> {
-    let mut width: u32 = 0;
-    let mut height: u32 = 0;
-    let mut bpp: u32 = 0;
-    let mut channels: u32 = 0;
-    let mut buffer = vec![0u8; buffer_size];
-    match unsafe {
-        bindings::GetQHYCCDSingleFrame(
-            handle.ptr,
-            &mut width as *mut u32,
-            &mut height as *mut u32,
-            &mut bpp as *mut u32,
-            &mut channels as *mut u32,
-            buffer.as_mut_ptr(),
-        )
-    } {
-        bindings::QHYCCD_SUCCESS => Ok(ImageData {
-            data: buffer,
-            width,
-            height,
-            bits_per_pixel: bpp,
-            channels,
-        }),
+    let mut image = ImageData {
+        data: vec![0u8; buffer_size],
+        ..Default::default()
+    };
+    match backend().get_qhyccd_single_frame(handle, &mut image) {
+        bindings::QHYCCD_SUCCESS => Ok(image),
         error_code => {
             let error = QHYError::GetSingleFrameError { error_code };
             tracing::error!(error = error.to_string().as_str());
@@ -639,26 +2149,119 @@ pub fn get_single_frame(handle: QhyccdHandle, buffer_size: usize) -> Result<Imag
         }
     }
 }
-pub fn get_overscan_area(handle: QhyccdHandle) -> Result<CCDChipArea> {
-    let mut start_x: u32 = 0;
-    let mut start_y: u32 = 0;
-    let mut width: u32 = 0;
-    let mut height: u32 = 0;
-    match unsafe {
-        bindings::GetQHYCCDOverScanArea(
-            handle.ptr,
-            &mut start_x as *mut u32,
-            &mut start_y as *mut u32,
-            &mut width as *mut u32,
-            &mut height as *mut u32,
-        )
-    } {
-        bindings::QHYCCD_SUCCESS => Ok(CCDChipArea {
-            start_x,
-            start_y,
-            width,
-            height,
+/// Expose and read a single frame, applying the given options
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{capture_single_frame, open_camera, RbiMitigation, SingleFrameOptions};
+/// let camera = open_camera("QHY990-1f2e3d4c5b6a7988".to_string()).unwrap();
+/// let options = SingleFrameOptions {
+///     rbi_mitigation: Some(RbiMitigation {
+///         pre_flash: true,
+///         flush_frames: 2,
+///     }),
+///     ..Default::default()
+/// };
+/// let frame = capture_single_frame(camera, &options).unwrap();
+/// ```
+pub fn capture_single_frame(
+    handle: QhyccdHandle,
+    options: &SingleFrameOptions,
+) -> Result<SingleFrame> {
+    let sdk_removes_overscan =
+        match is_feature_supported(handle, CameraFeature::CamIgnoreOverscanInterface) {
+            Ok(_) => {
+                let ignore = options.overscan == Overscan::Remove;
+                set_ignore_overscan(handle, ignore)?;
+                ignore
+            }
+            Err(_) => false,
+        };
+    if let Some(rbi) = options.rbi_mitigation {
+        match is_feature_supported(handle, CameraFeature::ControlRemoveRbi) {
+            Ok(_) => set_remove_rbi(handle, rbi.pre_flash)?,
+            Err(_) if rbi.pre_flash => {
+                let error = QHYError::UnsupportedFeatureError {
+                    feature: CameraFeature::ControlRemoveRbi,
+                };
+                tracing::error!(error = error.to_string().as_str());
+                return Err(eyre!(error));
+            }
+            Err(_) => {}
+        }
+        for flush in 0..rbi.flush_frames {
+            tracing::trace!(rbi_flush_frame = flush);
+            start_single_frame_exposure(handle)?;
+            get_single_frame(handle, get_image_size(handle)?)?;
+        }
+    }
+    start_single_frame_exposure(handle)?;
+    let image = get_single_frame(handle, get_image_size(handle)?)?;
+    match options.overscan {
+        Overscan::Include => Ok(SingleFrame {
+            image,
+            overscan: None,
         }),
+        Overscan::Remove if sdk_removes_overscan => Ok(SingleFrame {
+            image,
+            overscan: None,
+        }),
+        Overscan::Remove => Ok(SingleFrame {
+            image: image.crop(area_in_frame(handle, get_effective_area(handle)?)?)?,
+            overscan: None,
+        }),
+        Overscan::RemoveKeepStrip => Ok(SingleFrame {
+            overscan: Some(image.crop(area_in_frame(handle, get_overscan_area(handle)?)?)?),
+            image: image.crop(area_in_frame(handle, get_effective_area(handle)?)?)?,
+        }),
+    }
+}
+
+/// Convert an area in unbinned full frame coordinates to the part of it inside the current
+/// binned sub frame, relative to the sub frame
+fn area_in_frame(handle: QhyccdHandle, area: CCDChipArea) -> Result<CCDChipArea> {
+    let (bin_x, bin_y) = bin_factors(handle);
+    let roi = get_roi(handle)?;
+    // clamp both edges to the sub frame so areas outside of it become empty
+    let clamp = |value: u64, bin: u32, start: u32, length: u32| {
+        ((value / bin as u64).clamp(start as u64, start as u64 + length as u64) - start as u64)
+            as u32
+    };
+    let start_x = clamp(area.start_x as u64, bin_x, roi.start_x, roi.width);
+    let start_y = clamp(area.start_y as u64, bin_y, roi.start_y, roi.height);
+    let end_x = clamp(
+        area.start_x as u64 + area.width as u64,
+        bin_x,
+        roi.start_x,
+        roi.width,
+    );
+    let end_y = clamp(
+        area.start_y as u64 + area.height as u64,
+        bin_y,
+        roi.start_y,
+        roi.height,
+    );
+    Ok(CCDChipArea {
+        start_x,
+        start_y,
+        width: end_x - start_x,
+        height: end_y - start_y,
+    })
+}
+
+pub fn set_ignore_overscan(handle: QhyccdHandle, on: bool) -> Result<()> {
+    set_parameter(
+        handle,
+        CameraFeature::CamIgnoreOverscanInterface,
+        if on { 1.0 } else { 0.0 },
+    )
+}
+
+pub fn get_overscan_area(handle: QhyccdHandle) -> Result<CCDChipArea> {
+    let mut area = CCDChipArea::default();
+    match backend().get_qhyccd_overscan_area(handle, &mut area) {
+        bindings::QHYCCD_SUCCESS => Ok(area),
         error_code => {
             let error = QHYError::GetOverscanAreaError { error_code };
             tracing::error!(error = error.to_string().as_str());
@@ -668,25 +2271,9 @@ pub fn get_overscan_area(handle: QhyccdHandle) -> Result<CCDChipArea> {
 }
 
 pub fn get_effective_area(handle: QhyccdHandle) -> Result<CCDChipArea> {
-    let mut start_x: u32 = 0;
-    let mut start_y: u32 = 0;
-    let mut width: u32 = 0;
-    let mut height: u32 = 0;
-    match unsafe {
-        bindings::GetQHYCCDEffectiveArea(
-            handle.ptr,
-            &mut start_x as *mut u32,
-            &mut start_y as *mut u32,
-            &mut width as *mut u32,
-            &mut height as *mut u32,
-        )
-    } {
-        bindings::QHYCCD_SUCCESS => Ok(CCDChipArea {
-            start_x,
-            start_y,
-            width,
-            height,
-        }),
+    let mut area = CCDChipArea::default();
+    match backend().get_qhyccd_effective_area(handle, &mut area) {
+        bindings::QHYCCD_SUCCESS => Ok(area),
         error_code => {
             let error = QHYError::GetEffectiveAreaError { error_code };
             tracing::error!(error = error.to_string().as_str());
@@ -696,7 +2283,7 @@ pub fn get_effective_area(handle: QhyccdHandle) -> Result<CCDChipArea> {
 }
 
 pub fn start_single_frame_exposure(handle: QhyccdHandle) -> Result<()> {
-    match unsafe { bindings::ExpQHYCCDSingleFrame(handle.ptr) } {
+    match backend().exp_qhyccd_single_frame(handle) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::StartSingleFrameExposureError { error_code };
@@ -708,7 +2295,7 @@ pub fn start_single_frame_exposure(handle: QhyccdHandle) -> Result<()> {
 
 pub fn get_number_of_readout_modes(handle: QhyccdHandle) -> Result<u32> {
     let mut num: u32 = 0;
-    match unsafe { bindings::GetQHYCCDNumberOfReadModes(handle.ptr, &mut num as *mut u32) } {
+    match backend().get_qhyccd_number_of_read_modes(handle, &mut num) {
         bindings::QHYCCD_ERROR => {
             let error = QHYError::GetNumberOfReadoutModesError;
             tracing::error!(error = error.to_string().as_str());
@@ -719,37 +2306,21 @@ pub fn get_number_of_readout_modes(handle: QhyccdHandle) -> Result<u32> {
 }
 
 pub fn get_readout_mode_name(handle: QhyccdHandle, index: u32) -> Result<String> {
-    let mut name: [c_char; 80] = [0; 80];
-    match unsafe { bindings::GetQHYCCDReadModeName(handle.ptr, index, name.as_mut_ptr()) } {
+    let mut name = [0u8; 80];
+    match backend().get_qhyccd_read_mode_name(handle, index, &mut name) {
         bindings::QHYCCD_ERROR => {
             let error = QHYError::GetReadoutModeNameError;
             tracing::error!(error = error.to_string().as_str());
             Err(eyre!(error))
         }
-        _ => {
-            let name = match unsafe { CStr::from_ptr(name.as_ptr()) }.to_str() {
-                Ok(name) => name,
-                Err(error) => {
-                    tracing::error!(error = error.to_string().as_str());
-                    return Err(eyre!(error));
-                }
-            };
-            Ok(name.to_string())
-        }
+        _ => c_string_to_string(&name),
     }
 }
 
 pub fn get_readout_mode_resolution(handle: QhyccdHandle, index: u32) -> Result<(u32, u32)> {
     let mut width: u32 = 0;
     let mut height: u32 = 0;
-    match unsafe {
-        bindings::GetQHYCCDReadModeResolution(
-            handle.ptr,
-            index,
-            &mut width as *mut u32,
-            &mut height as *mut u32,
-        )
-    } {
+    match backend().get_qhyccd_read_mode_resolution(handle, index, &mut width, &mut height) {
         bindings::QHYCCD_SUCCESS => Ok((width, height)),
         _ => {
             let error = QHYError::GetReadoutModeResolutionError;
@@ -759,10 +2330,11 @@ pub fn get_readout_mode_resolution(handle: QhyccdHandle, index: u32) -> Result<(
     }
 }
 
-pub fn get_readout_mode(handle: QhyccdHandle) -> Result<u32> {
+/// Get the readout mode the camera is currently set to
+pub fn get_readout_mode(handle: QhyccdHandle) -> Result<ReadoutMode> {
     let mut mode: u32 = 0;
-    match unsafe { bindings::GetQHYCCDReadMode(handle.ptr, &mut mode as *mut u32) } {
-        bindings::QHYCCD_SUCCESS => Ok(mode),
+    match backend().get_qhyccd_read_mode(handle, &mut mode) {
+        bindings::QHYCCD_SUCCESS => get_readout_mode_info(handle, mode),
         _ => {
             let error = QHYError::GetReadoutModeError;
             tracing::error!(error = error.to_string().as_str());
@@ -771,19 +2343,58 @@ pub fn get_readout_mode(handle: QhyccdHandle) -> Result<u32> {
     }
 }
 
-pub fn get_model(handle: QhyccdHandle) -> Result<String> {
-    let mut model: [c_char; 80] = [0; 80];
-    match unsafe { bindings::GetQHYCCDModel(handle.ptr, model.as_mut_ptr()) } {
-        bindings::QHYCCD_SUCCESS => {
-            let model = match unsafe { CStr::from_ptr(model.as_ptr()) }.to_str() {
-                Ok(model) => model,
-                Err(error) => {
-                    tracing::error!(error = error.to_string().as_str());
-                    return Err(eyre!(error));
-                }
+fn get_readout_mode_info(handle: QhyccdHandle, id: u32) -> Result<ReadoutMode> {
+    let name = get_readout_mode_name(handle, id)?;
+    let (width, height) = get_readout_mode_resolution(handle, id)?;
+    Ok(ReadoutMode {
+        id,
+        name,
+        width,
+        height,
+    })
+}
+
+/// Get all readout modes of the camera including their resolution
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{open_camera, readout_modes};
+/// let camera = open_camera("QHY178M-222b16468c5966524".to_string()).unwrap();
+/// for mode in readout_modes(camera).unwrap() {
+///     println!("{}: {} {}x{}", mode.id, mode.name, mode.width, mode.height);
+/// }
+/// ```
+pub fn readout_modes(handle: QhyccdHandle) -> Result<Vec<ReadoutMode>> {
+    (0..get_number_of_readout_modes(handle)?)
+        .map(|id| get_readout_mode_info(handle, id))
+        .collect()
+}
+
+/// Set the readout mode with the given name, e.g. "High Gain Mode"
+pub fn set_readout_mode_by_name(handle: QhyccdHandle, name: &str) -> Result<ReadoutMode> {
+    match readout_modes(handle)?
+        .into_iter()
+        .find(|mode| mode.name == name)
+    {
+        Some(mode) => {
+            set_readout_mode(handle, mode.id)?;
+            Ok(mode)
+        }
+        None => {
+            let error = QHYError::ReadoutModeNotFoundError {
+                name: name.to_string(),
             };
-            Ok(model.to_string())
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
         }
+    }
+}
+
+pub fn get_model(handle: QhyccdHandle) -> Result<String> {
+    let mut model = [0u8; 80];
+    match backend().get_qhyccd_model(handle, &mut model) {
+        bindings::QHYCCD_SUCCESS => c_string_to_string(&model),
         error_code => {
             let error = QHYError::GetCameraModelError { error_code };
             tracing::error!(error = error.to_string().as_str());
@@ -793,7 +2404,7 @@ pub fn get_model(handle: QhyccdHandle) -> Result<String> {
 }
 
 pub fn get_type(handle: QhyccdHandle) -> Result<u32> {
-    match unsafe { bindings::GetQHYCCDType(handle.ptr) } {
+    match backend().get_qhyccd_type(handle) {
         bindings::QHYCCD_ERROR => {
             let error = QHYError::GetCameraTypeError;
             tracing::error!(error = error.to_string().as_str());
@@ -804,7 +2415,7 @@ pub fn get_type(handle: QhyccdHandle) -> Result<u32> {
 }
 
 pub fn get_remaining_exposure_us(handle: QhyccdHandle) -> Result<u32> {
-    match unsafe { bindings::GetQHYCCDExposureRemaining(handle.ptr) } {
+    match backend().get_qhyccd_exposure_remaining(handle) {
         bindings::QHYCCD_ERROR => {
             let error = QHYError::GetExposureRemainingError;
             tracing::error!(error = error.to_string().as_str());
@@ -816,7 +2427,7 @@ pub fn get_remaining_exposure_us(handle: QhyccdHandle) -> Result<u32> {
 }
 
 pub fn stop_exposure(handle: QhyccdHandle) -> Result<()> {
-    match unsafe { bindings::CancelQHYCCDExposing(handle.ptr) } {
+    match backend().cancel_qhyccd_exposing(handle) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::StopExposureError { error_code };
@@ -827,7 +2438,7 @@ pub fn stop_exposure(handle: QhyccdHandle) -> Result<()> {
 }
 
 pub fn abort_exposure_and_readout(handle: QhyccdHandle) -> Result<()> {
-    match unsafe { bindings::CancelQHYCCDExposingAndReadout(handle.ptr) } {
+    match backend().cancel_qhyccd_exposing_and_readout(handle) {
         bindings::QHYCCD_SUCCESS => Ok(()),
         error_code => {
             let error = QHYError::AbortExposureAndReadoutError { error_code };
@@ -836,3 +2447,248 @@ pub fn abort_exposure_and_readout(handle: QhyccdHandle) -> Result<()> {
         }
     }
 }
+
+/// Configure how the camera merges the high gain and low gain channels of a dual gain sensor
+pub fn set_hdr_combine(handle: QhyccdHandle, combine: HdrCombine) -> Result<()> {
+    ensure_sdk_function(SdkFunction::SetQHYCCDTwoChannelCombineParameter)?;
+    match backend().set_qhyccd_two_channel_combine_parameter(handle, combine) {
+        bindings::QHYCCD_SUCCESS => Ok(()),
+        error_code => {
+            let error = QHYError::SetHdrCombineError { error_code };
+            tracing::error!(error = error.to_string().as_str());
+            Err(eyre!(error))
+        }
+    }
+}
+
+/// Merge a high gain and a low gain 16 bit channel the same way the camera does
+///
+/// This allows raw dual gain data to be reprocessed offline with different parameters.
+pub fn combine_hdr_channels(
+    high_gain: &ImageData,
+    low_gain: &ImageData,
+    combine: HdrCombine,
+) -> Result<ImageData> {
+    if high_gain.bits_per_pixel != 16
+        || low_gain.bits_per_pixel != 16
+        || high_gain.width != low_gain.width
+        || high_gain.height != low_gain.height
+        || high_gain.channels != low_gain.channels
+        || high_gain.data.len() != low_gain.data.len()
+        || high_gain.data.len() % 2 != 0
+    {
+        let error = QHYError::CombineHdrChannelsError;
+        tracing::error!(error = error.to_string().as_str());
+        return Err(eyre!(error));
+    }
+    let data = high_gain
+        .data
+        .chunks_exact(2)
+        .zip(low_gain.data.chunks_exact(2))
+        .flat_map(|(high, low)| {
+            let high = u16::from_le_bytes([high[0], high[1]]) as f64;
+            let low = u16::from_le_bytes([low[0], low[1]]) as f64;
+            let value = if high < combine.threshold {
+                combine.high_gain_scale * high + combine.high_gain_offset
+            } else {
+                combine.low_gain_scale * low + combine.low_gain_offset
+            };
+            (value.round().clamp(0.0, u16::MAX as f64) as u16).to_le_bytes()
+        })
+        .collect();
+    Ok(ImageData {
+        data,
+        width: high_gain.width,
+        height: high_gain.height,
+        bits_per_pixel: 16,
+        channels: high_gain.channels,
+    })
+}
+
+/// Live capture that tags every frame with a frame number and tracks dropped and
+/// duplicated frames
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{begin_live, get_image_size, open_camera, LiveSession};
+/// let camera = open_camera("QHY178M-222b16468c5966524".to_string()).unwrap();
+/// begin_live(camera).unwrap();
+/// let mut session = LiveSession::new(camera, get_image_size(camera).unwrap());
+/// let frame = session.next_frame().unwrap();
+/// println!("{:?} {:?}", frame.frame_number, session.statistics());
+/// ```
+#[derive(Debug)]
+pub struct LiveSession {
+    handle: QhyccdHandle,
+    buffer_size: usize,
+    has_hardware_counter: bool,
+    host_sequence: u32,
+    last_hardware_counter: Option<u32>,
+    statistics: LiveStatistics,
+    ddr_warning_level: Option<f64>,
+}
+
+impl LiveSession {
+    pub fn new(handle: QhyccdHandle, buffer_size: usize) -> Self {
+        let has_hardware_counter =
+            is_feature_supported(handle, CameraFeature::HasHardwareFrameCounter).is_ok();
+        tracing::trace!(has_hardware_counter = has_hardware_counter);
+        Self {
+            handle,
+            buffer_size,
+            has_hardware_counter,
+            host_sequence: 0,
+            last_hardware_counter: None,
+            statistics: LiveStatistics::default(),
+            ddr_warning_level: None,
+        }
+    }
+
+    /// Emit a warning whenever the DDR buffer fill ratio reaches `level` (0.0 to 1.0)
+    pub fn warn_on_ddr_fill(mut self, level: f64) -> Self {
+        self.ddr_warning_level = Some(level);
+        self
+    }
+
+    /// Get the next live frame, see `get_live_frame`
+    pub fn next_frame(&mut self) -> Result<LiveFrame> {
+        let image = get_live_frame(self.handle, self.buffer_size)?;
+        let frame_number = match self.has_hardware_counter {
+            true => {
+                let counter =
+                    get_parameter(self.handle, CameraFeature::HasHardwareFrameCounter)? as u32;
+                self.record_hardware_counter(counter);
+                FrameNumber::Hardware(counter)
+            }
+            false => {
+                self.statistics.delivered += 1;
+                FrameNumber::Host(self.host_sequence)
+            }
+        };
+        self.host_sequence = self.host_sequence.wrapping_add(1);
+        if let Some(level) = self.ddr_warning_level {
+            // the frame is already read, a failed DDR query is logged but does not discard it
+            match get_ddr_buffer(self.handle) {
+                Ok(ddr) if ddr.fill_ratio() >= level => tracing::warn!(
+                    ddr_fill = ddr.fill,
+                    ddr_capacity = ddr.capacity,
+                    "DDR buffer is close to overflowing"
+                ),
+                Ok(_) => {}
+                Err(error) => tracing::warn!(
+                    error = error.to_string().as_str(),
+                    "could not read the DDR buffer fill level"
+                ),
+            }
+        }
+        Ok(LiveFrame {
+            image,
+            frame_number,
+        })
+    }
+
+    pub fn statistics(&self) -> LiveStatistics {
+        self.statistics
+    }
+
+    fn record_hardware_counter(&mut self, counter: u32) {
+        if let Some(last) = self.last_hardware_counter {
+            let gap = counter.wrapping_sub(last);
+            // a step backwards means an older frame was delivered again
+            if gap == 0 || gap > u32::MAX / 2 {
+                self.statistics.duplicated += 1;
+                tracing::warn!(duplicated_frame = counter);
+                return;
+            }
+            if gap > 1 {
+                self.statistics.dropped += (gap - 1) as u64;
+                tracing::warn!(dropped_frames = gap - 1, frame = counter);
+            }
+        }
+        self.statistics.delivered += 1;
+        self.last_hardware_counter = Some(counter);
+    }
+}
+
+/// Background thread polling `get_sensor_health` and reporting changes as events
+///
+/// The thread stops when the monitor is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{open_camera, SensorHealthMonitor};
+/// # use std::time::Duration;
+/// let camera = open_camera("QHY600M-f2f4a0f0b4a0c5d1".to_string()).unwrap();
+/// let (monitor, events) = SensorHealthMonitor::start(camera, Duration::from_secs(5));
+/// for event in events.iter() {
+///     println!("{:?}", event);
+/// }
+/// # drop(monitor);
+/// ```
+#[derive(Debug)]
+pub struct SensorHealthMonitor {
+    /// dropping the sender wakes the thread and stops it
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SensorHealthMonitor {
+    pub fn start(handle: QhyccdHandle, interval: Duration) -> (Self, Receiver<SensorHealthEvent>) {
+        let (sender, receiver) = channel();
+        let (stop, stopped) = channel();
+        let thread = std::thread::spawn(move || {
+            let mut previous = None;
+            let mut available = true;
+            loop {
+                let event = match get_sensor_health(handle) {
+                    Ok(current) if previous != Some(current) => {
+                        let event = SensorHealthEvent::Changed { previous, current };
+                        previous = Some(current);
+                        available = true;
+                        Some(event)
+                    }
+                    Ok(_) => {
+                        available = true;
+                        None
+                    }
+                    Err(_) if available => {
+                        available = false;
+                        Some(SensorHealthEvent::Unavailable)
+                    }
+                    Err(_) => None,
+                };
+                if let Some(event) = event {
+                    tracing::debug!(sensor_health = ?event);
+                    if sender.send(event).is_err() {
+                        break;
+                    }
+                }
+                if stopped.recv_timeout(interval) != Err(RecvTimeoutError::Timeout) {
+                    break;
+                }
+            }
+        });
+        (
+            Self {
+                stop: Some(stop),
+                thread: Some(thread),
+            },
+            receiver,
+        )
+    }
+
+    pub fn stop(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SensorHealthMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,398 @@
+//! Serialization of SDK calls, the vendor SDK is not documented as thread safe
+//!
+//! Every call made by the functions of the crate goes through `Serialized`, whatever backend is
+//! installed, which guarantees:
+//!
+//! - SDK wide functions (`InitQHYCCDResource`, `ReleaseQHYCCDResource`, `ScanQHYCCD`,
+//!   `GetQHYCCDId`, `GetQHYCCDSDKVersion`, `OpenQHYCCD` and `CloseQHYCCD`) run alone, they
+//!   wait for all running calls and block all others.
+//! - Calls on one camera run one at a time, calls on different cameras run concurrently.
+//! - `GetQHYCCDExposureRemaining`, `CancelQHYCCDExposing` and
+//!   `CancelQHYCCDExposingAndReadout` skip the camera lock, they are meant to be called while
+//!   another thread waits in `GetQHYCCDSingleFrame` and would otherwise wait for the frame. They
+//!   only wait for an SDK wide function that is running, not for one waiting to run.
+//!
+//! A call that blocks for the exposure, like `GetQHYCCDSingleFrame`, holds its camera for the
+//! whole exposure and delays SDK wide functions until it returns.
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use crate::{
+    CCDChipArea, CCDChipInfo, CameraBackend, CameraFeature, HdrCombine, ImageData, QhyccdHandle,
+    SdkFunction,
+};
+
+/// Held shared by calls on a camera and exclusively by SDK wide calls
+static SDK_LOCK: SdkLock = SdkLock::new();
+
+/// Readers-writer lock that lets `read_now` pass writers waiting for the lock
+///
+/// `std::sync::RwLock` may queue new readers behind a waiting writer, which would make stopping
+/// an exposure wait for the exposure when an SDK wide call is waiting for it too.
+struct SdkLock {
+    state: Mutex<SdkLockState>,
+    changed: Condvar,
+}
+
+struct SdkLockState {
+    readers: usize,
+    writer: bool,
+    waiting_writers: usize,
+}
+
+/// Releases the `SdkLock` when dropped
+struct SdkGuard {
+    lock: &'static SdkLock,
+    exclusive: bool,
+}
+
+impl SdkLock {
+    const fn new() -> Self {
+        Self {
+            state: Mutex::new(SdkLockState {
+                readers: 0,
+                writer: false,
+                waiting_writers: 0,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn state(&'static self) -> MutexGuard<'static, SdkLockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wait_while(
+        &'static self,
+        state: MutexGuard<'static, SdkLockState>,
+        condition: impl FnMut(&mut SdkLockState) -> bool,
+    ) -> MutexGuard<'static, SdkLockState> {
+        self.changed
+            .wait_while(state, condition)
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Shared, after the running and the waiting writers
+    fn read(&'static self) -> SdkGuard {
+        let mut state = self.wait_while(self.state(), |state| {
+            state.writer || state.waiting_writers > 0
+        });
+        state.readers += 1;
+        SdkGuard {
+            lock: self,
+            exclusive: false,
+        }
+    }
+
+    /// Shared, only after a running writer
+    fn read_now(&'static self) -> SdkGuard {
+        let mut state = self.wait_while(self.state(), |state| state.writer);
+        state.readers += 1;
+        SdkGuard {
+            lock: self,
+            exclusive: false,
+        }
+    }
+
+    fn write(&'static self) -> SdkGuard {
+        let mut state = self.state();
+        state.waiting_writers += 1;
+        let mut state = self.wait_while(state, |state| state.writer || state.readers > 0);
+        state.waiting_writers -= 1;
+        state.writer = true;
+        SdkGuard {
+            lock: self,
+            exclusive: true,
+        }
+    }
+}
+
+impl Drop for SdkGuard {
+    fn drop(&mut self) {
+        let mut state = self.lock.state();
+        match self.exclusive {
+            true => state.writer = false,
+            false => state.readers -= 1,
+        }
+        self.lock.changed.notify_all();
+    }
+}
+
+/// Lock per open camera, keyed by the handle pointer
+static CAMERA_LOCKS: Mutex<Option<HashMap<usize, Arc<Mutex<()>>>>> = Mutex::new(None);
+
+fn camera_locks() -> MutexGuard<'static, Option<HashMap<usize, Arc<Mutex<()>>>>> {
+    CAMERA_LOCKS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn camera_lock(handle: QhyccdHandle) -> Arc<Mutex<()>> {
+    camera_locks()
+        .get_or_insert_with(HashMap::new)
+        .entry(handle.ptr as usize)
+        .or_default()
+        .clone()
+}
+
+/// `CameraBackend` taking the locks described in the module documentation around every call
+pub(crate) struct Serialized {
+    inner: Arc<dyn CameraBackend>,
+}
+
+impl Serialized {
+    pub(crate) fn new(inner: Arc<dyn CameraBackend>) -> Self {
+        Self { inner }
+    }
+
+    fn sdk<T>(&self, f: impl FnOnce(&dyn CameraBackend) -> T) -> T {
+        let _sdk = SDK_LOCK.write();
+        f(&*self.inner)
+    }
+
+    fn camera<T>(&self, handle: QhyccdHandle, f: impl FnOnce(&dyn CameraBackend) -> T) -> T {
+        let _sdk = SDK_LOCK.read();
+        let camera = camera_lock(handle);
+        let _camera = camera.lock().unwrap_or_else(|e| e.into_inner());
+        f(&*self.inner)
+    }
+
+    /// Calls on a camera that may run while it is exposing
+    fn exposing<T>(&self, f: impl FnOnce(&dyn CameraBackend) -> T) -> T {
+        let _sdk = SDK_LOCK.read_now();
+        f(&*self.inner)
+    }
+}
+
+impl CameraBackend for Serialized {
+    fn load(&self) -> Result<(), String> {
+        self.inner.load()
+    }
+
+    fn is_available(&self, function: SdkFunction) -> bool {
+        self.inner.is_available(function)
+    }
+
+    fn init_qhyccd_resource(&self) -> u32 {
+        self.sdk(|inner| inner.init_qhyccd_resource())
+    }
+
+    fn release_qhyccd_resource(&self) -> u32 {
+        self.sdk(|inner| {
+            *camera_locks() = None;
+            inner.release_qhyccd_resource()
+        })
+    }
+
+    fn scan_qhyccd(&self) -> u32 {
+        self.sdk(|inner| inner.scan_qhyccd())
+    }
+
+    fn get_qhyccd_sdk_version(
+        &self,
+        year: &mut u32,
+        month: &mut u32,
+        day: &mut u32,
+        subday: &mut u32,
+    ) -> u32 {
+        self.sdk(|inner| inner.get_qhyccd_sdk_version(year, month, day, subday))
+    }
+
+    fn get_qhyccd_id(&self, index: u32, id: &mut [u8]) -> u32 {
+        self.sdk(|inner| inner.get_qhyccd_id(index, id))
+    }
+
+    fn open_qhyccd(&self, id: &str) -> QhyccdHandle {
+        self.sdk(|inner| inner.open_qhyccd(id))
+    }
+
+    fn close_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        self.sdk(|inner| {
+            if let Some(locks) = camera_locks().as_mut() {
+                locks.remove(&(handle.ptr as usize));
+            }
+            inner.close_qhyccd(handle)
+        })
+    }
+
+    fn init_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        self.camera(handle, |inner| inner.init_qhyccd(handle))
+    }
+
+    fn get_qhyccd_fw_version(&self, handle: QhyccdHandle, version: &mut [u8]) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_fw_version(handle, version))
+    }
+
+    fn get_qhyccd_model(&self, handle: QhyccdHandle, model: &mut [u8]) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_model(handle, model))
+    }
+
+    fn get_qhyccd_type(&self, handle: QhyccdHandle) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_type(handle))
+    }
+
+    fn is_qhyccd_control_available(&self, handle: QhyccdHandle, feature: CameraFeature) -> u32 {
+        self.camera(handle, |inner| {
+            inner.is_qhyccd_control_available(handle, feature)
+        })
+    }
+
+    fn set_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: u32) -> u32 {
+        self.camera(handle, |inner| inner.set_qhyccd_read_mode(handle, mode))
+    }
+
+    fn get_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: &mut u32) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_read_mode(handle, mode))
+    }
+
+    fn get_qhyccd_number_of_read_modes(&self, handle: QhyccdHandle, modes: &mut u32) -> u32 {
+        self.camera(handle, |inner| {
+            inner.get_qhyccd_number_of_read_modes(handle, modes)
+        })
+    }
+
+    fn get_qhyccd_read_mode_name(&self, handle: QhyccdHandle, mode: u32, name: &mut [u8]) -> u32 {
+        self.camera(handle, |inner| {
+            inner.get_qhyccd_read_mode_name(handle, mode, name)
+        })
+    }
+
+    fn get_qhyccd_read_mode_resolution(
+        &self,
+        handle: QhyccdHandle,
+        mode: u32,
+        width: &mut u32,
+        height: &mut u32,
+    ) -> u32 {
+        self.camera(handle, |inner| {
+            inner.get_qhyccd_read_mode_resolution(handle, mode, width, height)
+        })
+    }
+
+    fn set_qhyccd_stream_mode(&self, handle: QhyccdHandle, mode: u8) -> u32 {
+        self.camera(handle, |inner| inner.set_qhyccd_stream_mode(handle, mode))
+    }
+
+    fn get_qhyccd_chip_info(&self, handle: QhyccdHandle, info: &mut CCDChipInfo) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_chip_info(handle, info))
+    }
+
+    fn get_qhyccd_overscan_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_overscan_area(handle, area))
+    }
+
+    fn get_qhyccd_effective_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        self.camera(handle, |inner| {
+            inner.get_qhyccd_effective_area(handle, area)
+        })
+    }
+
+    fn set_qhyccd_bits_mode(&self, handle: QhyccdHandle, bits: u32) -> u32 {
+        self.camera(handle, |inner| inner.set_qhyccd_bits_mode(handle, bits))
+    }
+
+    fn set_qhyccd_debayer_on_off(&self, handle: QhyccdHandle, on: bool) -> u32 {
+        self.camera(handle, |inner| inner.set_qhyccd_debayer_on_off(handle, on))
+    }
+
+    fn set_qhyccd_bin_mode(&self, handle: QhyccdHandle, bin_x: u32, bin_y: u32) -> u32 {
+        self.camera(handle, |inner| {
+            inner.set_qhyccd_bin_mode(handle, bin_x, bin_y)
+        })
+    }
+
+    fn set_qhyccd_resolution(&self, handle: QhyccdHandle, roi: CCDChipArea) -> u32 {
+        self.camera(handle, |inner| inner.set_qhyccd_resolution(handle, roi))
+    }
+
+    fn get_qhyccd_current_roi(&self, handle: QhyccdHandle, roi: &mut CCDChipArea) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_current_roi(handle, roi))
+    }
+
+    fn set_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature, value: f64) -> u32 {
+        self.camera(handle, |inner| {
+            inner.set_qhyccd_param(handle, feature, value)
+        })
+    }
+
+    fn get_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature) -> f64 {
+        self.camera(handle, |inner| inner.get_qhyccd_param(handle, feature))
+    }
+
+    fn get_qhyccd_param_min_max_step(
+        &self,
+        handle: QhyccdHandle,
+        feature: CameraFeature,
+        min: &mut f64,
+        max: &mut f64,
+        step: &mut f64,
+    ) -> u32 {
+        self.camera(handle, |inner| {
+            inner.get_qhyccd_param_min_max_step(handle, feature, min, max, step)
+        })
+    }
+
+    fn get_qhyccd_mem_length(&self, handle: QhyccdHandle) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_mem_length(handle))
+    }
+
+    fn exp_qhyccd_single_frame(&self, handle: QhyccdHandle) -> u32 {
+        self.camera(handle, |inner| inner.exp_qhyccd_single_frame(handle))
+    }
+
+    fn get_qhyccd_single_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_single_frame(handle, image))
+    }
+
+    fn begin_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        self.camera(handle, |inner| inner.begin_qhyccd_live(handle))
+    }
+
+    fn get_qhyccd_live_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_live_frame(handle, image))
+    }
+
+    fn stop_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        self.camera(handle, |inner| inner.stop_qhyccd_live(handle))
+    }
+
+    fn get_qhyccd_exposure_remaining(&self, handle: QhyccdHandle) -> u32 {
+        self.exposing(|inner| inner.get_qhyccd_exposure_remaining(handle))
+    }
+
+    fn cancel_qhyccd_exposing(&self, handle: QhyccdHandle) -> u32 {
+        self.exposing(|inner| inner.cancel_qhyccd_exposing(handle))
+    }
+
+    fn cancel_qhyccd_exposing_and_readout(&self, handle: QhyccdHandle) -> u32 {
+        self.exposing(|inner| inner.cancel_qhyccd_exposing_and_readout(handle))
+    }
+
+    fn get_qhyccd_humidity(&self, handle: QhyccdHandle, humidity: &mut f64) -> u32 {
+        self.camera(handle, |inner| inner.get_qhyccd_humidity(handle, humidity))
+    }
+
+    fn qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        self.camera(handle, |inner| inner.qhyccd_sensor_phase_retrain(handle))
+    }
+
+    fn get_qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        self.camera(handle, |inner| {
+            inner.get_qhyccd_sensor_phase_retrain(handle)
+        })
+    }
+
+    fn set_qhyccd_enable_live_mode_anti_rbi(&self, handle: QhyccdHandle, value: u32) -> u32 {
+        self.camera(handle, |inner| {
+            inner.set_qhyccd_enable_live_mode_anti_rbi(handle, value)
+        })
+    }
+
+    fn set_qhyccd_two_channel_combine_parameter(
+        &self,
+        handle: QhyccdHandle,
+        combine: HdrCombine,
+    ) -> u32 {
+        self.camera(handle, |inner| {
+            inner.set_qhyccd_two_channel_combine_parameter(handle, combine)
+        })
+    }
+}
@@ -0,0 +1,833 @@
+//! In-process backend for testing code built on the crate without a camera or the SDK
+use std::collections::{HashMap, VecDeque};
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::backend::write_c_string;
+use crate::simulator::{Readout, SensorModel};
+use crate::{
+    bindings, CCDChipArea, CCDChipInfo, CameraBackend, CameraFeature, HdrCombine, ImageData,
+    QhyccdHandle, SDKVersion, SdkFunction,
+};
+
+/// Scriptable `CameraBackend` that records the SDK functions called on it
+///
+/// Clones share their state, so a test keeps one clone to script and inspect the mock and
+/// hands another to `set_backend`. Without scripting every call succeeds and behaves like a
+/// 640x480 16 bit mono camera, frames are black unless queued with `push_frame` or rendered by
+/// a sensor model set with `with_sensor`.
+///
+/// # Example
+///
+/// ```
+/// # use libqhyccd_sys::{
+/// #     get_camera_id, get_image_size, get_single_frame, init_sdk, open_camera, scan_qhyccd,
+/// #     set_backend, start_single_frame_exposure, MockBackend, SdkFunction,
+/// # };
+/// let mock = MockBackend::new();
+/// mock.return_codes(SdkFunction::GetQHYCCDSingleFrame, [0xFFFF_FFFF]);
+/// let _backend = set_backend(mock.clone());
+///
+/// init_sdk().unwrap();
+/// scan_qhyccd().unwrap();
+/// let camera = open_camera(get_camera_id(0).unwrap()).unwrap();
+/// start_single_frame_exposure(camera).unwrap();
+/// let size = get_image_size(camera).unwrap();
+/// assert!(get_single_frame(camera, size).is_err());
+/// assert!(get_single_frame(camera, size).is_ok());
+///
+/// assert_eq!(
+///     mock.calls()[3..],
+///     ["GetQHYCCDId", "OpenQHYCCD", "ExpQHYCCDSingleFrame", "GetQHYCCDMemLength",
+///      "GetQHYCCDSingleFrame", "GetQHYCCDSingleFrame"]
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockBackend {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug)]
+struct MockState {
+    sdk_version: SDKVersion,
+    missing_functions: Vec<SdkFunction>,
+    camera_ids: Vec<String>,
+    model: String,
+    chip_info: CCDChipInfo,
+    overscan_area: CCDChipArea,
+    effective_area: CCDChipArea,
+    readout_modes: Vec<(String, u32, u32)>,
+    features: Vec<CameraFeature>,
+    ranges: HashMap<u32, (f64, f64, f64)>,
+    parameters: HashMap<u32, f64>,
+    open: Vec<bool>,
+    readout_mode: u32,
+    roi: CCDChipArea,
+    binning: (u32, u32),
+    bits: u32,
+    frames: VecDeque<ImageData>,
+    sensor: Option<SensorModel>,
+    rendered_frames: u64,
+    humidity: f64,
+    return_codes: HashMap<&'static str, VecDeque<u32>>,
+    latencies: HashMap<&'static str, Duration>,
+    calls: Vec<&'static str>,
+}
+
+const DEFAULT_RANGES: [(CameraFeature, f64, f64, f64); 5] = [
+    (CameraFeature::ControlGain, 0.0, 100.0, 1.0),
+    (CameraFeature::ControlOffset, 0.0, 255.0, 1.0),
+    (CameraFeature::ControlExposure, 1.0, 3_600_000_000.0, 1.0),
+    (CameraFeature::ControlUsbTraffic, 0.0, 255.0, 1.0),
+    (CameraFeature::ControlTransferBit, 8.0, 16.0, 8.0),
+];
+
+impl Default for MockState {
+    fn default() -> Self {
+        let chip_info = CCDChipInfo {
+            chip_width: 640.0 * 0.0024,
+            chip_height: 480.0 * 0.0024,
+            image_width: 640,
+            image_height: 480,
+            pixel_width: 2.4,
+            pixel_height: 2.4,
+            bits_per_pixel: 16,
+        };
+        let full_frame = CCDChipArea {
+            start_x: 0,
+            start_y: 0,
+            width: chip_info.image_width,
+            height: chip_info.image_height,
+        };
+        let mut state = Self {
+            sdk_version: SDKVersion {
+                year: 23,
+                month: 9,
+                day: 6,
+                subday: 14,
+            },
+            missing_functions: Vec::new(),
+            camera_ids: vec!["QHY178M-mock0".to_string()],
+            model: "QHY178M".to_string(),
+            chip_info,
+            overscan_area: CCDChipArea::default(),
+            effective_area: full_frame,
+            readout_modes: vec![(
+                "STANDARD MODE".to_string(),
+                chip_info.image_width,
+                chip_info.image_height,
+            )],
+            features: vec![
+                CameraFeature::CamSingleFrameMode,
+                CameraFeature::CamLiveVideoMode,
+                CameraFeature::CamBin1x1mode,
+                CameraFeature::CamBin2x2mode,
+                CameraFeature::Cam8bits,
+                CameraFeature::Cam16bits,
+            ],
+            ranges: HashMap::new(),
+            parameters: HashMap::new(),
+            open: vec![false],
+            readout_mode: 0,
+            roi: full_frame,
+            binning: (1, 1),
+            bits: 16,
+            frames: VecDeque::new(),
+            sensor: None,
+            rendered_frames: 0,
+            humidity: 20.0,
+            return_codes: HashMap::new(),
+            latencies: HashMap::new(),
+            calls: Vec::new(),
+        };
+        for (feature, min, max, step) in DEFAULT_RANGES {
+            state.set_range(feature, min, max, step);
+        }
+        state
+    }
+}
+
+impl MockState {
+    fn set_range(&mut self, feature: CameraFeature, min: f64, max: f64, step: f64) {
+        if !self.features.contains(&feature) {
+            self.features.push(feature);
+        }
+        self.ranges.insert(feature as u32, (min, max, step));
+        self.parameters.entry(feature as u32).or_insert(min);
+    }
+
+    /// Index of the open camera behind `handle`, handles are the camera index plus one
+    fn camera(&self, handle: QhyccdHandle) -> Option<usize> {
+        let index = (handle.ptr as usize).checked_sub(1)?;
+        match self.open.get(index) {
+            Some(true) => Some(index),
+            _ => None,
+        }
+    }
+
+    fn next_frame(&mut self) -> ImageData {
+        if let Some(frame) = self.frames.pop_front() {
+            return frame;
+        }
+        match &self.sensor {
+            Some(sensor) => {
+                let readout = Readout {
+                    sensor_width: self.chip_info.image_width,
+                    sensor_height: self.chip_info.image_height,
+                    roi: self.roi,
+                    binning: self.binning,
+                    bits: self.bits,
+                    parameters: &self.parameters,
+                };
+                let frame = sensor.render(&readout, self.rendered_frames);
+                self.rendered_frames += 1;
+                frame
+            }
+            None => ImageData {
+                data: vec![0; (self.roi.width * self.roi.height * self.bits / 8) as usize],
+                width: self.roi.width,
+                height: self.roi.height,
+                bits_per_pixel: self.bits,
+                channels: 1,
+            },
+        }
+    }
+
+    fn read_frame(&mut self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        if self.camera(handle).is_none() {
+            return bindings::QHYCCD_ERROR;
+        }
+        let frame = self.next_frame();
+        if frame.data.len() > image.data.len() {
+            return bindings::QHYCCD_ERROR;
+        }
+        image.data[..frame.data.len()].copy_from_slice(&frame.data);
+        image.width = frame.width;
+        image.height = frame.height;
+        image.bits_per_pixel = frame.bits_per_pixel;
+        image.channels = frame.channels;
+        bindings::QHYCCD_SUCCESS
+    }
+}
+
+fn status(ok: bool) -> u32 {
+    match ok {
+        true => bindings::QHYCCD_SUCCESS,
+        false => bindings::QHYCCD_ERROR,
+    }
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cameras reported by `ScanQHYCCD` and `GetQHYCCDId`
+    pub fn with_cameras(self, ids: &[&str]) -> Self {
+        {
+            let mut state = self.lock();
+            state.camera_ids = ids.iter().map(|id| id.to_string()).collect();
+            state.open = vec![false; ids.len()];
+        }
+        self
+    }
+
+    pub fn with_model(self, model: &str) -> Self {
+        self.lock().model = model.to_string();
+        self
+    }
+
+    pub fn with_sdk_version(self, version: SDKVersion) -> Self {
+        self.lock().sdk_version = version;
+        self
+    }
+
+    /// Pretend the SDK does not export `function`
+    pub fn without_function(self, function: SdkFunction) -> Self {
+        self.lock().missing_functions.push(function);
+        self
+    }
+
+    /// Sensor geometry, the effective area, sub frame and readout mode cover the whole chip
+    pub fn with_chip_info(self, info: CCDChipInfo) -> Self {
+        {
+            let mut state = self.lock();
+            let full_frame = CCDChipArea {
+                start_x: 0,
+                start_y: 0,
+                width: info.image_width,
+                height: info.image_height,
+            };
+            state.chip_info = info;
+            state.effective_area = full_frame;
+            state.roi = full_frame;
+            state.bits = info.bits_per_pixel;
+            for mode in state.readout_modes.iter_mut() {
+                mode.1 = info.image_width;
+                mode.2 = info.image_height;
+            }
+        }
+        self
+    }
+
+    pub fn with_overscan(self, overscan_area: CCDChipArea, effective_area: CCDChipArea) -> Self {
+        {
+            let mut state = self.lock();
+            state.overscan_area = overscan_area;
+            state.effective_area = effective_area;
+        }
+        self
+    }
+
+    /// Readout modes as name, width and height
+    pub fn with_readout_modes(self, modes: &[(&str, u32, u32)]) -> Self {
+        self.lock().readout_modes = modes
+            .iter()
+            .map(|(name, width, height)| (name.to_string(), *width, *height))
+            .collect();
+        self
+    }
+
+    /// Features `IsQHYCCDControlAvailable` reports as supported, replacing the defaults
+    pub fn with_features(self, features: &[CameraFeature]) -> Self {
+        self.lock().features = features.to_vec();
+        self
+    }
+
+    /// Mark a parameter as supported with the given range, its value starts at `min`
+    pub fn with_parameter_range(
+        self,
+        feature: CameraFeature,
+        min: f64,
+        max: f64,
+        step: f64,
+    ) -> Self {
+        self.lock().set_range(feature, min, max, step);
+        self
+    }
+
+    /// Render frames with `sensor` from the current sub frame, binning, bit depth, exposure,
+    /// gain, offset and `ControlCurTemp`, which starts at 25 °C
+    pub fn with_sensor(self, sensor: SensorModel) -> Self {
+        {
+            let mut state = self.lock();
+            if sensor.bayer.is_some() && !state.features.contains(&CameraFeature::CamColor) {
+                state.features.push(CameraFeature::CamColor);
+            }
+            state.set_range(CameraFeature::ControlCurTemp, -50.0, 50.0, 0.1);
+            state
+                .parameters
+                .insert(CameraFeature::ControlCurTemp as u32, 25.0);
+            state.sensor = Some(sensor);
+        }
+        self
+    }
+
+    /// Change the sensor model set with `with_sensor`, e.g. the defocus during an autofocus run
+    pub fn update_sensor(&self, f: impl FnOnce(&mut SensorModel)) {
+        if let Some(sensor) = self.lock().sensor.as_mut() {
+            f(sensor);
+        }
+    }
+
+    /// Queue a frame for the next `GetQHYCCDSingleFrame` or `GetQHYCCDLiveFrame`
+    pub fn push_frame(&self, image: ImageData) {
+        self.lock().frames.push_back(image);
+    }
+
+    /// Return these codes from the next calls of `function`, e.g. `GetQHYCCDLiveFrame`
+    ///
+    /// A scripted `QHYCCD_SUCCESS` still performs the call, any other code is returned without
+    /// side effects. For functions returning a value, like `ScanQHYCCD`, `GetQHYCCDParam` or
+    /// the handle of `OpenQHYCCD`, the code is the value.
+    pub fn return_codes(&self, function: SdkFunction, codes: impl IntoIterator<Item = u32>) {
+        self.lock()
+            .return_codes
+            .entry(function.name())
+            .or_default()
+            .extend(codes);
+    }
+
+    /// Block every call of `function` for `latency`, e.g. to simulate exposure and readout
+    pub fn set_latency(&self, function: SdkFunction, latency: Duration) {
+        self.lock().latencies.insert(function.name(), latency);
+    }
+
+    /// SDK functions called so far, in order
+    pub fn calls(&self) -> Vec<&'static str> {
+        self.lock().calls.clone()
+    }
+
+    pub fn clear_calls(&self) {
+        self.lock().calls.clear();
+    }
+
+    /// Current value of a parameter as set through `SetQHYCCDParam`
+    pub fn parameter(&self, feature: CameraFeature) -> Option<f64> {
+        self.lock().parameters.get(&(feature as u32)).copied()
+    }
+
+    /// Change a parameter as the camera would, e.g. `ControlCurTemp` while cooling
+    pub fn set_parameter(&self, feature: CameraFeature, value: f64) {
+        self.lock().parameters.insert(feature as u32, value);
+    }
+
+    /// Change the sensor chamber humidity returned by `GetQHYCCDHumidity`
+    pub fn set_humidity(&self, humidity: f64) {
+        self.lock().humidity = humidity;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record the call and apply its latency, a scripted code is turned into the result by
+    /// `scripted` unless that returns `None`, otherwise `f` runs
+    fn dispatch<T>(
+        &self,
+        function: &'static str,
+        scripted: impl FnOnce(u32) -> Option<T>,
+        f: impl FnOnce(&mut MockState) -> T,
+    ) -> T {
+        let (result, latency) = {
+            let mut state = self.lock();
+            state.calls.push(function);
+            let result = match state
+                .return_codes
+                .get_mut(function)
+                .and_then(VecDeque::pop_front)
+                .and_then(scripted)
+            {
+                Some(result) => result,
+                None => f(&mut state),
+            };
+            (result, state.latencies.get(function).copied())
+        };
+        if let Some(latency) = latency {
+            std::thread::sleep(latency);
+        }
+        result
+    }
+
+    /// Functions returning a status code, a scripted `QHYCCD_SUCCESS` still runs `f`
+    fn call(&self, function: &'static str, f: impl FnOnce(&mut MockState) -> u32) -> u32 {
+        self.dispatch(
+            function,
+            |code| (code != bindings::QHYCCD_SUCCESS).then_some(code),
+            f,
+        )
+    }
+
+    /// Functions returning a value, a scripted code replaces the value
+    fn value(&self, function: &'static str, f: impl FnOnce(&mut MockState) -> u32) -> u32 {
+        self.dispatch(function, Some, f)
+    }
+}
+
+impl CameraBackend for MockBackend {
+    fn is_available(&self, function: SdkFunction) -> bool {
+        !self.lock().missing_functions.contains(&function)
+    }
+
+    fn init_qhyccd_resource(&self) -> u32 {
+        self.call("InitQHYCCDResource", |_| bindings::QHYCCD_SUCCESS)
+    }
+
+    fn release_qhyccd_resource(&self) -> u32 {
+        self.call("ReleaseQHYCCDResource", |state| {
+            state.open.iter_mut().for_each(|open| *open = false);
+            bindings::QHYCCD_SUCCESS
+        })
+    }
+
+    fn scan_qhyccd(&self) -> u32 {
+        self.value("ScanQHYCCD", |state| state.camera_ids.len() as u32)
+    }
+
+    fn get_qhyccd_sdk_version(
+        &self,
+        year: &mut u32,
+        month: &mut u32,
+        day: &mut u32,
+        subday: &mut u32,
+    ) -> u32 {
+        self.call("GetQHYCCDSDKVersion", |state| {
+            *year = state.sdk_version.year;
+            *month = state.sdk_version.month;
+            *day = state.sdk_version.day;
+            *subday = state.sdk_version.subday;
+            bindings::QHYCCD_SUCCESS
+        })
+    }
+
+    fn get_qhyccd_id(&self, index: u32, id: &mut [u8]) -> u32 {
+        self.call("GetQHYCCDId", |state| {
+            match state.camera_ids.get(index as usize) {
+                Some(camera_id) => {
+                    write_c_string(id, camera_id.as_bytes());
+                    bindings::QHYCCD_SUCCESS
+                }
+                None => bindings::QHYCCD_ERROR,
+            }
+        })
+    }
+
+    fn open_qhyccd(&self, id: &str) -> QhyccdHandle {
+        let handle = self.value("OpenQHYCCD", |state| {
+            match state
+                .camera_ids
+                .iter()
+                .position(|camera_id| camera_id == id)
+            {
+                Some(index) => {
+                    state.open[index] = true;
+                    index as u32 + 1
+                }
+                None => 0,
+            }
+        });
+        match handle {
+            bindings::QHYCCD_ERROR => QhyccdHandle::new(std::ptr::null_mut()),
+            handle => QhyccdHandle::new(handle as usize as *mut c_void),
+        }
+    }
+
+    fn close_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        self.call("CloseQHYCCD", |state| match state.camera(handle) {
+            Some(index) => {
+                state.open[index] = false;
+                bindings::QHYCCD_SUCCESS
+            }
+            None => bindings::QHYCCD_ERROR,
+        })
+    }
+
+    fn init_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        self.call("InitQHYCCD", |state| status(state.camera(handle).is_some()))
+    }
+
+    fn get_qhyccd_fw_version(&self, handle: QhyccdHandle, version: &mut [u8]) -> u32 {
+        self.call("GetQHYCCDFWVersion", |state| {
+            if version.len() >= 2 {
+                version[0] = 0x79;
+                version[1] = 6;
+            }
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_model(&self, handle: QhyccdHandle, model: &mut [u8]) -> u32 {
+        self.call("GetQHYCCDModel", |state| {
+            write_c_string(model, state.model.as_bytes());
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_type(&self, handle: QhyccdHandle) -> u32 {
+        self.value("GetQHYCCDType", |state| match state.camera(handle) {
+            Some(_) => 4010,
+            None => bindings::QHYCCD_ERROR,
+        })
+    }
+
+    fn is_qhyccd_control_available(&self, handle: QhyccdHandle, feature: CameraFeature) -> u32 {
+        self.value("IsQHYCCDControlAvailable", |state| {
+            let bayer = state.sensor.as_ref().and_then(|sensor| sensor.bayer);
+            match bayer {
+                // color cameras report their Bayer pattern
+                Some(bayer) if feature == CameraFeature::CamColor => match state.camera(handle) {
+                    Some(_) => bayer as u32,
+                    None => bindings::QHYCCD_ERROR,
+                },
+                _ => status(state.camera(handle).is_some() && state.features.contains(&feature)),
+            }
+        })
+    }
+
+    fn set_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: u32) -> u32 {
+        self.call("SetQHYCCDReadMode", |state| {
+            let ok = state.camera(handle).is_some() && (mode as usize) < state.readout_modes.len();
+            if ok {
+                state.readout_mode = mode;
+            }
+            status(ok)
+        })
+    }
+
+    fn get_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: &mut u32) -> u32 {
+        self.call("GetQHYCCDReadMode", |state| {
+            *mode = state.readout_mode;
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_number_of_read_modes(&self, handle: QhyccdHandle, modes: &mut u32) -> u32 {
+        self.call("GetQHYCCDNumberOfReadModes", |state| {
+            *modes = state.readout_modes.len() as u32;
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_read_mode_name(&self, handle: QhyccdHandle, mode: u32, name: &mut [u8]) -> u32 {
+        self.call("GetQHYCCDReadModeName", |state| {
+            match (state.camera(handle), state.readout_modes.get(mode as usize)) {
+                (Some(_), Some((mode_name, _, _))) => {
+                    write_c_string(name, mode_name.as_bytes());
+                    bindings::QHYCCD_SUCCESS
+                }
+                _ => bindings::QHYCCD_ERROR,
+            }
+        })
+    }
+
+    fn get_qhyccd_read_mode_resolution(
+        &self,
+        handle: QhyccdHandle,
+        mode: u32,
+        width: &mut u32,
+        height: &mut u32,
+    ) -> u32 {
+        self.call("GetQHYCCDReadModeResolution", |state| {
+            match (state.camera(handle), state.readout_modes.get(mode as usize)) {
+                (Some(_), Some((_, mode_width, mode_height))) => {
+                    *width = *mode_width;
+                    *height = *mode_height;
+                    bindings::QHYCCD_SUCCESS
+                }
+                _ => bindings::QHYCCD_ERROR,
+            }
+        })
+    }
+
+    fn set_qhyccd_stream_mode(&self, handle: QhyccdHandle, mode: u8) -> u32 {
+        self.call("SetQHYCCDStreamMode", |state| {
+            status(state.camera(handle).is_some() && mode <= 1)
+        })
+    }
+
+    fn get_qhyccd_chip_info(&self, handle: QhyccdHandle, info: &mut CCDChipInfo) -> u32 {
+        self.call("GetQHYCCDChipInfo", |state| {
+            *info = CCDChipInfo {
+                bits_per_pixel: state.bits,
+                ..state.chip_info
+            };
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_overscan_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        self.call("GetQHYCCDOverScanArea", |state| {
+            *area = state.overscan_area;
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_effective_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        self.call("GetQHYCCDEffectiveArea", |state| {
+            *area = state.effective_area;
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn set_qhyccd_bits_mode(&self, handle: QhyccdHandle, bits: u32) -> u32 {
+        self.call("SetQHYCCDBitsMode", |state| {
+            let ok = state.camera(handle).is_some() && matches!(bits, 8 | 16 | 32);
+            if ok {
+                state.bits = bits;
+            }
+            status(ok)
+        })
+    }
+
+    fn set_qhyccd_debayer_on_off(&self, handle: QhyccdHandle, _on: bool) -> u32 {
+        self.call("SetQHYCCDDebayerOnOff", |state| {
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn set_qhyccd_bin_mode(&self, handle: QhyccdHandle, bin_x: u32, bin_y: u32) -> u32 {
+        self.call("SetQHYCCDBinMode", |state| {
+            let ok = state.camera(handle).is_some() && bin_x > 0 && bin_y > 0;
+            if ok {
+                state.binning = (bin_x, bin_y);
+            }
+            status(ok)
+        })
+    }
+
+    fn set_qhyccd_resolution(&self, handle: QhyccdHandle, roi: CCDChipArea) -> u32 {
+        self.call("SetQHYCCDResolution", |state| {
+            let fits = |start: u32, size: u32, limit: u32| {
+                matches!(start.checked_add(size), Some(end) if end <= limit)
+            };
+            let ok = state.camera(handle).is_some()
+                && roi.width > 0
+                && roi.height > 0
+                && fits(
+                    roi.start_x,
+                    roi.width,
+                    state.chip_info.image_width / state.binning.0,
+                )
+                && fits(
+                    roi.start_y,
+                    roi.height,
+                    state.chip_info.image_height / state.binning.1,
+                );
+            if ok {
+                state.roi = roi;
+            }
+            status(ok)
+        })
+    }
+
+    fn get_qhyccd_current_roi(&self, handle: QhyccdHandle, roi: &mut CCDChipArea) -> u32 {
+        self.call("GetQHYCCDCurrentROI", |state| {
+            *roi = state.roi;
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn set_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature, value: f64) -> u32 {
+        self.call("SetQHYCCDParam", |state| {
+            let in_range = match state.ranges.get(&(feature as u32)) {
+                Some((min, max, _)) => value >= *min && value <= *max,
+                None => state.features.contains(&feature),
+            };
+            let ok = state.camera(handle).is_some() && in_range;
+            if ok {
+                state.parameters.insert(feature as u32, value);
+            }
+            status(ok)
+        })
+    }
+
+    fn get_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature) -> f64 {
+        self.dispatch(
+            "GetQHYCCDParam",
+            |code| Some(code as f64),
+            |state| match (
+                state.camera(handle),
+                state.parameters.get(&(feature as u32)),
+            ) {
+                (Some(_), Some(value)) => *value,
+                _ => bindings::QHYCCD_ERROR as f64,
+            },
+        )
+    }
+
+    fn get_qhyccd_param_min_max_step(
+        &self,
+        handle: QhyccdHandle,
+        feature: CameraFeature,
+        min: &mut f64,
+        max: &mut f64,
+        step: &mut f64,
+    ) -> u32 {
+        self.call("GetQHYCCDParamMinMaxStep", |state| {
+            match (state.camera(handle), state.ranges.get(&(feature as u32))) {
+                (Some(_), Some(range)) => {
+                    (*min, *max, *step) = *range;
+                    bindings::QHYCCD_SUCCESS
+                }
+                _ => bindings::QHYCCD_ERROR,
+            }
+        })
+    }
+
+    fn get_qhyccd_mem_length(&self, handle: QhyccdHandle) -> u32 {
+        self.value("GetQHYCCDMemLength", |state| match state.camera(handle) {
+            Some(_) => state.chip_info.image_width * state.chip_info.image_height * 2,
+            None => bindings::QHYCCD_ERROR,
+        })
+    }
+
+    fn exp_qhyccd_single_frame(&self, handle: QhyccdHandle) -> u32 {
+        self.call("ExpQHYCCDSingleFrame", |state| {
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_single_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        self.call("GetQHYCCDSingleFrame", |state| {
+            state.read_frame(handle, image)
+        })
+    }
+
+    fn begin_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        self.call("BeginQHYCCDLive", |state| {
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_live_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        self.call("GetQHYCCDLiveFrame", |state| {
+            state.read_frame(handle, image)
+        })
+    }
+
+    fn stop_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        self.call("StopQHYCCDLive", |state| {
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_exposure_remaining(&self, handle: QhyccdHandle) -> u32 {
+        self.value("GetQHYCCDExposureRemaining", |state| {
+            match state.camera(handle) {
+                Some(_) => 0,
+                None => bindings::QHYCCD_ERROR,
+            }
+        })
+    }
+
+    fn cancel_qhyccd_exposing(&self, handle: QhyccdHandle) -> u32 {
+        self.call("CancelQHYCCDExposing", |state| {
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn cancel_qhyccd_exposing_and_readout(&self, handle: QhyccdHandle) -> u32 {
+        self.call("CancelQHYCCDExposingAndReadout", |state| {
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_humidity(&self, handle: QhyccdHandle, humidity: &mut f64) -> u32 {
+        self.call("GetQHYCCDHumidity", |state| {
+            *humidity = state.humidity;
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        self.call("QHYCCDSensorPhaseReTrain", |state| {
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn get_qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        self.value("GetQHYCCDSensorPhaseReTrain", |state| {
+            match state.camera(handle) {
+                Some(_) => 0,
+                None => bindings::QHYCCD_ERROR,
+            }
+        })
+    }
+
+    fn set_qhyccd_enable_live_mode_anti_rbi(&self, handle: QhyccdHandle, _value: u32) -> u32 {
+        self.call("SetQHYCCDEnableLiveModeAntiRBI", |state| {
+            status(state.camera(handle).is_some())
+        })
+    }
+
+    fn set_qhyccd_two_channel_combine_parameter(
+        &self,
+        handle: QhyccdHandle,
+        _combine: HdrCombine,
+    ) -> u32 {
+        self.call("SetQHYCCDTwoChannelCombineParameter", |state| {
+            status(state.camera(handle).is_some())
+        })
+    }
+}
@@ -0,0 +1,286 @@
+//! Physical model of a sensor for rendering synthetic frames in `MockBackend`
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::{BayerId, CCDChipArea, CameraFeature, ImageData};
+
+/// A star of the synthetic star field, positions are unbinned sensor pixels
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Star {
+    pub x: f64,
+    pub y: f64,
+    /// Photoelectrons per second collected from the star
+    pub flux: f64,
+}
+
+/// Noise sources, signal chain and scene of a simulated sensor
+///
+/// Electrons are collected from the sky background, the stars, dark current, hot pixels and
+/// amp glow, shot noise is applied and the charge of binned pixels is summed. Read noise is
+/// added per output pixel before conversion to ADU with `electrons_per_adu`, which drops by
+/// `gain_db_per_step` for every step of `ControlGain`. `ControlOffset` is added to `bias`.
+///
+/// # Example
+///
+/// ```
+/// # use libqhyccd_sys::{
+/// #     get_camera_id, get_image_size, get_single_frame, init_sdk, open_camera, scan_qhyccd,
+/// #     set_backend, set_exposure, start_single_frame_exposure, MockBackend, SensorModel, Star,
+/// # };
+/// # use std::time::Duration;
+/// let sensor = SensorModel {
+///     stars: vec![Star { x: 320.0, y: 240.0, flux: 50_000.0 }],
+///     ..Default::default()
+/// };
+/// let _backend = set_backend(MockBackend::new().with_sensor(sensor));
+///
+/// init_sdk().unwrap();
+/// scan_qhyccd().unwrap();
+/// let camera = open_camera(get_camera_id(0).unwrap()).unwrap();
+/// set_exposure(camera, Duration::from_secs(1)).unwrap();
+/// start_single_frame_exposure(camera).unwrap();
+/// let image = get_single_frame(camera, get_image_size(camera).unwrap()).unwrap();
+/// assert!(image.mean() > 0.0);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct SensorModel {
+    /// Bias level in 16 bit ADU
+    pub bias: f64,
+    /// Read noise in electrons
+    pub read_noise: f64,
+    /// Conversion factor at gain 0
+    pub electrons_per_adu: f64,
+    pub gain_db_per_step: f64,
+    /// Dark current in electrons per second at 25 °C
+    pub dark_current: f64,
+    /// Temperature increase doubling the dark current
+    pub dark_doubling_temperature: f64,
+    /// Fraction of pixels with `hot_pixel_current` added to their dark current
+    pub hot_pixel_fraction: f64,
+    pub hot_pixel_current: f64,
+    /// Electrons per second at the middle of the right sensor edge, falling off exponentially
+    pub amp_glow: f64,
+    pub amp_glow_radius: f64,
+    /// Color filter array reported through `CamColor`, `None` for a mono sensor
+    pub bayer: Option<BayerId>,
+    /// Relative response of red, green and blue pixels
+    pub channel_response: [f64; 3],
+    /// Sky background in electrons per second and pixel
+    pub sky_background: f64,
+    pub stars: Vec<Star>,
+    /// FWHM of the seeing disc in pixels
+    pub seeing: f64,
+    /// Diameter of the defocus blur in pixels, added in quadrature to `seeing`
+    pub defocus: f64,
+    /// Seed for hot pixel positions and noise, frames differ but are reproducible
+    pub seed: u64,
+}
+
+impl Default for SensorModel {
+    fn default() -> Self {
+        Self {
+            bias: 1000.0,
+            read_noise: 2.0,
+            electrons_per_adu: 1.0,
+            gain_db_per_step: 0.1,
+            dark_current: 0.01,
+            dark_doubling_temperature: 6.0,
+            hot_pixel_fraction: 0.0001,
+            hot_pixel_current: 50.0,
+            amp_glow: 0.0,
+            amp_glow_radius: 50.0,
+            bayer: None,
+            channel_response: [0.8, 1.0, 0.7],
+            sky_background: 1.0,
+            stars: Vec::new(),
+            seeing: 3.0,
+            defocus: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Camera state a frame is rendered for
+#[derive(Debug)]
+pub(crate) struct Readout<'a> {
+    pub sensor_width: u32,
+    pub sensor_height: u32,
+    /// Sub frame in binned pixels
+    pub roi: CCDChipArea,
+    pub binning: (u32, u32),
+    pub bits: u32,
+    pub parameters: &'a HashMap<u32, f64>,
+}
+
+impl Readout<'_> {
+    fn parameter(&self, feature: CameraFeature) -> Option<f64> {
+        self.parameters.get(&(feature as u32)).copied()
+    }
+}
+
+/// splitmix64, good enough for noise and without a dependency
+#[derive(Debug)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        mix(self.0)
+    }
+
+    fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.uniform();
+        (-2.0 * u.ln()).sqrt() * (2.0 * PI * self.uniform()).cos()
+    }
+
+    fn poisson(&mut self, mean: f64) -> f64 {
+        if mean <= 0.0 {
+            return 0.0;
+        }
+        if mean > 30.0 {
+            return (mean + mean.sqrt() * self.normal()).round().max(0.0);
+        }
+        let limit = (-mean).exp();
+        let (mut count, mut product) = (0.0, self.uniform());
+        while product > limit {
+            count += 1.0;
+            product *= self.uniform();
+        }
+        count
+    }
+}
+
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl BayerId {
+    /// Channel of the pixel at `x`, `y` as 0 red, 1 green, 2 blue
+    fn channel(&self, x: u32, y: u32) -> usize {
+        let pattern = match self {
+            BayerId::BayerGb => [1, 2, 0, 1],
+            BayerId::BayerGr => [1, 0, 2, 1],
+            BayerId::BayerBg => [2, 1, 1, 0],
+            BayerId::BayerRg => [0, 1, 1, 2],
+        };
+        pattern[(y as usize % 2) * 2 + x as usize % 2]
+    }
+}
+
+impl SensorModel {
+    /// Scatter `count` stars with fluxes up to `max_flux` over a sensor of the given size
+    pub fn with_random_stars(self, count: usize, width: u32, height: u32, max_flux: f64) -> Self {
+        let mut rng = Rng(self.seed ^ 0x5354_4152);
+        let stars = (0..count)
+            .map(|_| Star {
+                x: rng.uniform() * width as f64,
+                y: rng.uniform() * height as f64,
+                // faint stars are more common than bright ones
+                flux: max_flux * rng.uniform().powi(3),
+            })
+            .collect();
+        Self { stars, ..self }
+    }
+
+    fn is_hot_pixel(&self, x: u32, y: u32) -> bool {
+        let hash = mix(self.seed ^ ((x as u64) << 32 | y as u64));
+        ((hash >> 11) as f64 / (1u64 << 53) as f64) < self.hot_pixel_fraction
+    }
+
+    /// Render the frame number `frame` for the current camera state
+    pub(crate) fn render(&self, readout: &Readout, frame: u64) -> ImageData {
+        let mut rng = Rng(mix(self.seed.wrapping_add(frame)));
+        let exposure = readout
+            .parameter(CameraFeature::ControlExposure)
+            .unwrap_or(0.0)
+            / 1e6;
+        let temperature = readout
+            .parameter(CameraFeature::ControlCurTemp)
+            .unwrap_or(25.0);
+        let gain = readout.parameter(CameraFeature::ControlGain).unwrap_or(0.0);
+        let offset = readout
+            .parameter(CameraFeature::ControlOffset)
+            .unwrap_or(0.0);
+        let electrons_per_adu =
+            self.electrons_per_adu / 10f64.powf(gain * self.gain_db_per_step / 20.0);
+        let dark_current =
+            self.dark_current * 2f64.powf((temperature - 25.0) / self.dark_doubling_temperature);
+
+        // the sub frame in unbinned sensor pixels
+        let (bin_x, bin_y) = (readout.binning.0.max(1), readout.binning.1.max(1));
+        let (x0, y0) = (readout.roi.start_x * bin_x, readout.roi.start_y * bin_y);
+        let (width, height) = (readout.roi.width * bin_x, readout.roi.height * bin_y);
+
+        let mut scene = vec![self.sky_background; width as usize * height as usize];
+        let sigma = (self.seeing.powi(2) + self.defocus.powi(2)).sqrt().max(0.1) / 2.3548;
+        let reach = (5.0 * sigma).ceil();
+        for star in &self.stars {
+            let left = (star.x - reach).max(x0 as f64) as u32;
+            let right = (star.x + reach).min((x0 + width) as f64 - 1.0);
+            let top = (star.y - reach).max(y0 as f64) as u32;
+            let bottom = (star.y + reach).min((y0 + height) as f64 - 1.0);
+            if right < left as f64 || bottom < top as f64 {
+                continue;
+            }
+            for y in top..=bottom as u32 {
+                for x in left..=right as u32 {
+                    let r2 = (x as f64 + 0.5 - star.x).powi(2) + (y as f64 + 0.5 - star.y).powi(2);
+                    scene[((y - y0) * width + x - x0) as usize] += star.flux
+                        * (-r2 / (2.0 * sigma * sigma)).exp()
+                        / (2.0 * PI * sigma * sigma);
+                }
+            }
+        }
+
+        let glow_x = readout.sensor_width as f64;
+        let glow_y = readout.sensor_height as f64 / 2.0;
+        let max = ((1u32 << readout.bits.min(16)) - 1) as f64;
+        let mut data = Vec::with_capacity(readout.roi.width as usize * readout.roi.height as usize);
+        for by in 0..readout.roi.height {
+            for bx in 0..readout.roi.width {
+                let mut electrons = 0.0;
+                for y in y0 + by * bin_y..y0 + (by + 1) * bin_y {
+                    for x in x0 + bx * bin_x..x0 + (bx + 1) * bin_x {
+                        let light = scene[((y - y0) * width + x - x0) as usize];
+                        let response = match self.bayer {
+                            Some(bayer) => self.channel_response[bayer.channel(x, y)],
+                            None => 1.0,
+                        };
+                        let mut rate = light * response + dark_current;
+                        if self.is_hot_pixel(x, y) {
+                            rate += self.hot_pixel_current;
+                        }
+                        if self.amp_glow > 0.0 {
+                            let distance = (glow_x - x as f64).hypot(glow_y - y as f64);
+                            rate += self.amp_glow * (-distance / self.amp_glow_radius).exp();
+                        }
+                        electrons += rng.poisson(rate * exposure);
+                    }
+                }
+                let electrons = electrons + self.read_noise * rng.normal();
+                let adu = self.bias + offset + electrons / electrons_per_adu;
+                data.push((adu * max / 65535.0).round().clamp(0.0, max));
+            }
+        }
+
+        ImageData {
+            data: match readout.bits {
+                0..=8 => data.iter().map(|&value| value as u8).collect(),
+                _ => data
+                    .iter()
+                    .flat_map(|&value| (value as u16).to_le_bytes())
+                    .collect(),
+            },
+            width: readout.roi.width,
+            height: readout.roi.height,
+            bits_per_pixel: readout.bits,
+            channels: 1,
+        }
+    }
+}
@@ -0,0 +1,1374 @@
+//! Recording of the SDK calls made through a backend and replaying them without a camera
+//!
+//! A trace is a text file with one SDK call per line:
+//!
+//! ```text
+//! <start µs> <duration µs> <function> <arguments> = <result> <outputs>
+//! ```
+//!
+//! Strings are quoted and percent encoded, structs are comma separated fields and byte buffers
+//! are hex, optionally deflate compressed with a `z` prefix.
+use std::ffi::c_void;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use eyre::{eyre, Result};
+
+use crate::backend::{c_string_bytes, write_c_string};
+use crate::{
+    bindings, CCDChipArea, CCDChipInfo, CameraBackend, CameraFeature, HdrCombine, ImageData,
+    QHYError, QhyccdHandle, SdkBackend, SdkFunction,
+};
+
+const HEADER: &str = "# libqhyccd-sys trace 1";
+
+/// How `RecordingBackend` stores the image data of frames
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum FrameRecording {
+    #[default]
+    Raw,
+    /// Deflate compressed, needs the `trace-compression` feature for recording and replay
+    #[cfg(feature = "trace-compression")]
+    Compressed,
+    /// Only the frame size is recorded, replayed frames are black
+    Omitted,
+}
+
+/// One value of a trace line
+trait TraceValue {
+    fn write(&self, out: &mut String);
+}
+
+impl TraceValue for u32 {
+    fn write(&self, out: &mut String) {
+        write!(out, "{}", self).unwrap();
+    }
+}
+
+impl TraceValue for u8 {
+    fn write(&self, out: &mut String) {
+        write!(out, "{}", self).unwrap();
+    }
+}
+
+impl TraceValue for bool {
+    fn write(&self, out: &mut String) {
+        out.push(if *self { '1' } else { '0' });
+    }
+}
+
+impl TraceValue for f64 {
+    fn write(&self, out: &mut String) {
+        write!(out, "{:?}", self).unwrap();
+    }
+}
+
+impl TraceValue for str {
+    fn write(&self, out: &mut String) {
+        NulTerminated(self.as_bytes()).write(out);
+    }
+}
+
+/// The bytes of a C string before its nul, written like a `str`
+struct NulTerminated<'a>(&'a [u8]);
+
+impl TraceValue for NulTerminated<'_> {
+    fn write(&self, out: &mut String) {
+        out.push('"');
+        for &byte in self.0 {
+            match byte {
+                b'"' | b'%' | b',' | b'=' => write!(out, "%{:02X}", byte).unwrap(),
+                0x21..=0x7E => out.push(byte as char),
+                _ => write!(out, "%{:02X}", byte).unwrap(),
+            }
+        }
+        out.push('"');
+    }
+}
+
+impl TraceValue for String {
+    fn write(&self, out: &mut String) {
+        self.as_str().write(out);
+    }
+}
+
+impl TraceValue for [u8] {
+    fn write(&self, out: &mut String) {
+        for byte in self {
+            write!(out, "{:02x}", byte).unwrap();
+        }
+    }
+}
+
+impl TraceValue for CameraFeature {
+    fn write(&self, out: &mut String) {
+        (*self as u32).write(out);
+    }
+}
+
+impl TraceValue for QhyccdHandle {
+    fn write(&self, out: &mut String) {
+        write!(out, "{}", self.ptr as usize).unwrap();
+    }
+}
+
+impl TraceValue for CCDChipArea {
+    fn write(&self, out: &mut String) {
+        write!(
+            out,
+            "{},{},{},{}",
+            self.start_x, self.start_y, self.width, self.height
+        )
+        .unwrap();
+    }
+}
+
+impl TraceValue for CCDChipInfo {
+    fn write(&self, out: &mut String) {
+        write!(
+            out,
+            "{:?},{:?},{},{},{:?},{:?},{}",
+            self.chip_width,
+            self.chip_height,
+            self.image_width,
+            self.image_height,
+            self.pixel_width,
+            self.pixel_height,
+            self.bits_per_pixel
+        )
+        .unwrap();
+    }
+}
+
+impl TraceValue for HdrCombine {
+    fn write(&self, out: &mut String) {
+        write!(
+            out,
+            "{:?},{:?},{:?},{:?},{:?}",
+            self.threshold,
+            self.high_gain_scale,
+            self.high_gain_offset,
+            self.low_gain_scale,
+            self.low_gain_offset
+        )
+        .unwrap();
+    }
+}
+
+fn token(value: &(impl TraceValue + ?Sized)) -> String {
+    let mut out = String::new();
+    value.write(&mut out);
+    out
+}
+
+/// Size and data of a frame, the data is limited to what the frame header says was written
+fn frame_token(image: &ImageData, frames: FrameRecording) -> String {
+    let len = (image.width as usize * image.height as usize * image.bits_per_pixel as usize / 8
+        * image.channels.max(1) as usize)
+        .min(image.data.len());
+    let mut out = format!(
+        "{},{},{},{},",
+        image.width, image.height, image.bits_per_pixel, image.channels
+    );
+    match frames {
+        FrameRecording::Raw => image.data[..len].write(&mut out),
+        #[cfg(feature = "trace-compression")]
+        FrameRecording::Compressed => {
+            out.push('z');
+            let compressed = miniz_oxide::deflate::compress_to_vec(&image.data[..len], 6);
+            compressed.as_slice().write(&mut out);
+        }
+        FrameRecording::Omitted => out.push('-'),
+    }
+    out
+}
+
+fn parse_u32(token: &str) -> Option<u32> {
+    token.parse().ok()
+}
+
+fn parse_f64(token: &str) -> Option<f64> {
+    token.parse().ok()
+}
+
+fn parse_string(token: &str) -> Option<String> {
+    String::from_utf8(parse_c_string(token)?).ok()
+}
+
+fn parse_c_string(token: &str) -> Option<Vec<u8>> {
+    let inner = token.strip_prefix('"')?.strip_suffix('"')?;
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.bytes();
+    while let Some(byte) = chars.next() {
+        match byte {
+            b'%' => {
+                let hex = [chars.next()?, chars.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            byte => bytes.push(byte),
+        }
+    }
+    Some(bytes)
+}
+
+fn parse_bytes(token: &str) -> Option<Vec<u8>> {
+    if token.len() % 2 != 0 {
+        return None;
+    }
+    (0..token.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(token.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn parse_fields<const N: usize>(token: &str) -> Option<[&str; N]> {
+    let fields = token.splitn(N, ',').collect::<Vec<_>>();
+    fields.try_into().ok()
+}
+
+fn parse_area(token: &str) -> Option<CCDChipArea> {
+    let [start_x, start_y, width, height] = parse_fields(token)?;
+    Some(CCDChipArea {
+        start_x: parse_u32(start_x)?,
+        start_y: parse_u32(start_y)?,
+        width: parse_u32(width)?,
+        height: parse_u32(height)?,
+    })
+}
+
+fn parse_chip_info(token: &str) -> Option<CCDChipInfo> {
+    let [chip_width, chip_height, image_width, image_height, pixel_width, pixel_height, bpp] =
+        parse_fields(token)?;
+    Some(CCDChipInfo {
+        chip_width: parse_f64(chip_width)?,
+        chip_height: parse_f64(chip_height)?,
+        image_width: parse_u32(image_width)?,
+        image_height: parse_u32(image_height)?,
+        pixel_width: parse_f64(pixel_width)?,
+        pixel_height: parse_f64(pixel_height)?,
+        bits_per_pixel: parse_u32(bpp)?,
+    })
+}
+
+/// Fill `image` from a frame token, the data has to fit into `image.data`
+fn parse_frame(token: &str, image: &mut ImageData) -> Option<()> {
+    let [width, height, bpp, channels, data] = parse_fields(token)?;
+    image.width = parse_u32(width)?;
+    image.height = parse_u32(height)?;
+    image.bits_per_pixel = parse_u32(bpp)?;
+    image.channels = parse_u32(channels)?;
+    let data = match data {
+        "-" => return Some(()),
+        #[cfg(feature = "trace-compression")]
+        compressed if compressed.starts_with('z') => {
+            miniz_oxide::inflate::decompress_to_vec(&parse_bytes(&compressed[1..])?).ok()?
+        }
+        raw => parse_bytes(raw)?,
+    };
+    image.data.get_mut(..data.len())?.copy_from_slice(&data);
+    Some(())
+}
+
+/// Record every SDK call made through `inner` to a trace file
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{set_backend, FrameRecording, RecordingBackend};
+/// let recording = RecordingBackend::create("session.trace")
+///     .unwrap()
+///     .with_frames(FrameRecording::Omitted);
+/// let _backend = set_backend(recording);
+/// ```
+#[derive(Debug)]
+pub struct RecordingBackend<B = SdkBackend> {
+    inner: B,
+    frames: FrameRecording,
+    start: Instant,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl RecordingBackend<SdkBackend> {
+    /// Record the calls made to the QHYCCD SDK
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Self::wrap(SdkBackend, path)
+    }
+}
+
+impl<B: CameraBackend> RecordingBackend<B> {
+    pub fn wrap(inner: B, path: impl AsRef<Path>) -> Result<Self> {
+        let mut writer = match File::create(path.as_ref()) {
+            Ok(file) => BufWriter::new(file),
+            Err(error) => {
+                tracing::error!(error = error.to_string().as_str());
+                return Err(eyre!(error));
+            }
+        };
+        writeln!(writer, "{}", HEADER)?;
+        Ok(Self {
+            inner,
+            frames: FrameRecording::default(),
+            start: Instant::now(),
+            writer: Mutex::new(writer),
+        })
+    }
+
+    pub fn with_frames(self, frames: FrameRecording) -> Self {
+        Self { frames, ..self }
+    }
+
+    /// Append a call to the trace, flushing so the trace survives a crash
+    fn record(
+        &self,
+        start: Instant,
+        function: &str,
+        args: &[String],
+        result: String,
+        outputs: &[String],
+    ) {
+        let mut line = format!(
+            "{} {} {}",
+            start.duration_since(self.start).as_micros(),
+            start.elapsed().as_micros(),
+            function
+        );
+        for arg in args {
+            line.push(' ');
+            line.push_str(arg);
+        }
+        line.push_str(" = ");
+        line.push_str(&result);
+        for output in outputs {
+            line.push(' ');
+            line.push_str(output);
+        }
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(error) = writeln!(writer, "{}", line).and_then(|_| writer.flush()) {
+            tracing::error!(error = error.to_string().as_str(), "writing trace failed");
+        }
+    }
+}
+
+impl<B: CameraBackend> CameraBackend for RecordingBackend<B> {
+    fn load(&self) -> Result<(), String> {
+        let start = Instant::now();
+        let result = self.inner.load();
+        let reason = result.as_ref().err().map(String::as_str).unwrap_or("");
+        self.record(start, "load", &[], token(&result.is_ok()), &[token(reason)]);
+        result
+    }
+
+    fn is_available(&self, function: SdkFunction) -> bool {
+        let start = Instant::now();
+        let result = self.inner.is_available(function);
+        self.record(
+            start,
+            "is_available",
+            &[token(function.name())],
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn init_qhyccd_resource(&self) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.init_qhyccd_resource();
+        self.record(start, "InitQHYCCDResource", &[], token(&result), &[]);
+        result
+    }
+
+    fn release_qhyccd_resource(&self) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.release_qhyccd_resource();
+        self.record(start, "ReleaseQHYCCDResource", &[], token(&result), &[]);
+        result
+    }
+
+    fn scan_qhyccd(&self) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.scan_qhyccd();
+        self.record(start, "ScanQHYCCD", &[], token(&result), &[]);
+        result
+    }
+
+    fn get_qhyccd_sdk_version(
+        &self,
+        year: &mut u32,
+        month: &mut u32,
+        day: &mut u32,
+        subday: &mut u32,
+    ) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_sdk_version(year, month, day, subday);
+        let outputs = [token(year), token(month), token(day), token(subday)];
+        self.record(start, "GetQHYCCDSDKVersion", &[], token(&result), &outputs);
+        result
+    }
+
+    fn get_qhyccd_id(&self, index: u32, id: &mut [u8]) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_id(index, id);
+        self.record(
+            start,
+            "GetQHYCCDId",
+            &[token(&index)],
+            token(&result),
+            &[token(&NulTerminated(c_string_bytes(id)))],
+        );
+        result
+    }
+
+    fn open_qhyccd(&self, id: &str) -> QhyccdHandle {
+        let start = Instant::now();
+        let result = self.inner.open_qhyccd(id);
+        self.record(start, "OpenQHYCCD", &[token(id)], token(&result), &[]);
+        result
+    }
+
+    fn close_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.close_qhyccd(handle);
+        self.record(start, "CloseQHYCCD", &[token(&handle)], token(&result), &[]);
+        result
+    }
+
+    fn init_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.init_qhyccd(handle);
+        self.record(start, "InitQHYCCD", &[token(&handle)], token(&result), &[]);
+        result
+    }
+
+    fn get_qhyccd_fw_version(&self, handle: QhyccdHandle, version: &mut [u8]) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_fw_version(handle, version);
+        let outputs = [token(version)];
+        self.record(
+            start,
+            "GetQHYCCDFWVersion",
+            &[token(&handle)],
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn get_qhyccd_model(&self, handle: QhyccdHandle, model: &mut [u8]) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_model(handle, model);
+        let outputs = [token(&NulTerminated(c_string_bytes(model)))];
+        self.record(
+            start,
+            "GetQHYCCDModel",
+            &[token(&handle)],
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn get_qhyccd_type(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_type(handle);
+        self.record(
+            start,
+            "GetQHYCCDType",
+            &[token(&handle)],
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn is_qhyccd_control_available(&self, handle: QhyccdHandle, feature: CameraFeature) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.is_qhyccd_control_available(handle, feature);
+        let args = [token(&handle), token(&feature)];
+        self.record(
+            start,
+            "IsQHYCCDControlAvailable",
+            &args,
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn set_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: u32) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.set_qhyccd_read_mode(handle, mode);
+        let args = [token(&handle), token(&mode)];
+        self.record(start, "SetQHYCCDReadMode", &args, token(&result), &[]);
+        result
+    }
+
+    fn get_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: &mut u32) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_read_mode(handle, mode);
+        let outputs = [token(mode)];
+        self.record(
+            start,
+            "GetQHYCCDReadMode",
+            &[token(&handle)],
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn get_qhyccd_number_of_read_modes(&self, handle: QhyccdHandle, modes: &mut u32) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_number_of_read_modes(handle, modes);
+        let (args, outputs) = ([token(&handle)], [token(modes)]);
+        self.record(
+            start,
+            "GetQHYCCDNumberOfReadModes",
+            &args,
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn get_qhyccd_read_mode_name(&self, handle: QhyccdHandle, mode: u32, name: &mut [u8]) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_read_mode_name(handle, mode, name);
+        let outputs = [token(&NulTerminated(c_string_bytes(name)))];
+        let args = [token(&handle), token(&mode)];
+        self.record(
+            start,
+            "GetQHYCCDReadModeName",
+            &args,
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn get_qhyccd_read_mode_resolution(
+        &self,
+        handle: QhyccdHandle,
+        mode: u32,
+        width: &mut u32,
+        height: &mut u32,
+    ) -> u32 {
+        let start = Instant::now();
+        let result = self
+            .inner
+            .get_qhyccd_read_mode_resolution(handle, mode, width, height);
+        let (args, outputs) = (
+            [token(&handle), token(&mode)],
+            [token(width), token(height)],
+        );
+        self.record(
+            start,
+            "GetQHYCCDReadModeResolution",
+            &args,
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn set_qhyccd_stream_mode(&self, handle: QhyccdHandle, mode: u8) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.set_qhyccd_stream_mode(handle, mode);
+        let args = [token(&handle), token(&mode)];
+        self.record(start, "SetQHYCCDStreamMode", &args, token(&result), &[]);
+        result
+    }
+
+    fn get_qhyccd_chip_info(&self, handle: QhyccdHandle, info: &mut CCDChipInfo) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_chip_info(handle, info);
+        let outputs = [token(info)];
+        self.record(
+            start,
+            "GetQHYCCDChipInfo",
+            &[token(&handle)],
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn get_qhyccd_overscan_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_overscan_area(handle, area);
+        let (args, outputs) = ([token(&handle)], [token(area)]);
+        self.record(
+            start,
+            "GetQHYCCDOverScanArea",
+            &args,
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn get_qhyccd_effective_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_effective_area(handle, area);
+        let (args, outputs) = ([token(&handle)], [token(area)]);
+        self.record(
+            start,
+            "GetQHYCCDEffectiveArea",
+            &args,
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn set_qhyccd_bits_mode(&self, handle: QhyccdHandle, bits: u32) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.set_qhyccd_bits_mode(handle, bits);
+        let args = [token(&handle), token(&bits)];
+        self.record(start, "SetQHYCCDBitsMode", &args, token(&result), &[]);
+        result
+    }
+
+    fn set_qhyccd_debayer_on_off(&self, handle: QhyccdHandle, on: bool) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.set_qhyccd_debayer_on_off(handle, on);
+        let args = [token(&handle), token(&on)];
+        self.record(start, "SetQHYCCDDebayerOnOff", &args, token(&result), &[]);
+        result
+    }
+
+    fn set_qhyccd_bin_mode(&self, handle: QhyccdHandle, bin_x: u32, bin_y: u32) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.set_qhyccd_bin_mode(handle, bin_x, bin_y);
+        let args = [token(&handle), token(&bin_x), token(&bin_y)];
+        self.record(start, "SetQHYCCDBinMode", &args, token(&result), &[]);
+        result
+    }
+
+    fn set_qhyccd_resolution(&self, handle: QhyccdHandle, roi: CCDChipArea) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.set_qhyccd_resolution(handle, roi);
+        let args = [token(&handle), token(&roi)];
+        self.record(start, "SetQHYCCDResolution", &args, token(&result), &[]);
+        result
+    }
+
+    fn get_qhyccd_current_roi(&self, handle: QhyccdHandle, roi: &mut CCDChipArea) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_current_roi(handle, roi);
+        let (args, outputs) = ([token(&handle)], [token(roi)]);
+        self.record(
+            start,
+            "GetQHYCCDCurrentROI",
+            &args,
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn set_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature, value: f64) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.set_qhyccd_param(handle, feature, value);
+        let args = [token(&handle), token(&feature), token(&value)];
+        self.record(start, "SetQHYCCDParam", &args, token(&result), &[]);
+        result
+    }
+
+    fn get_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature) -> f64 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_param(handle, feature);
+        let args = [token(&handle), token(&feature)];
+        self.record(start, "GetQHYCCDParam", &args, token(&result), &[]);
+        result
+    }
+
+    fn get_qhyccd_param_min_max_step(
+        &self,
+        handle: QhyccdHandle,
+        feature: CameraFeature,
+        min: &mut f64,
+        max: &mut f64,
+        step: &mut f64,
+    ) -> u32 {
+        let start = Instant::now();
+        let result = self
+            .inner
+            .get_qhyccd_param_min_max_step(handle, feature, min, max, step);
+        let args = [token(&handle), token(&feature)];
+        let outputs = [token(min), token(max), token(step)];
+        self.record(
+            start,
+            "GetQHYCCDParamMinMaxStep",
+            &args,
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn get_qhyccd_mem_length(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_mem_length(handle);
+        self.record(
+            start,
+            "GetQHYCCDMemLength",
+            &[token(&handle)],
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn exp_qhyccd_single_frame(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.exp_qhyccd_single_frame(handle);
+        self.record(
+            start,
+            "ExpQHYCCDSingleFrame",
+            &[token(&handle)],
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn get_qhyccd_single_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_single_frame(handle, image);
+        let (args, outputs) = ([token(&handle)], [frame_token(image, self.frames)]);
+        self.record(
+            start,
+            "GetQHYCCDSingleFrame",
+            &args,
+            token(&result),
+            &outputs,
+        );
+        result
+    }
+
+    fn begin_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.begin_qhyccd_live(handle);
+        self.record(
+            start,
+            "BeginQHYCCDLive",
+            &[token(&handle)],
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn get_qhyccd_live_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_live_frame(handle, image);
+        let (args, outputs) = ([token(&handle)], [frame_token(image, self.frames)]);
+        self.record(start, "GetQHYCCDLiveFrame", &args, token(&result), &outputs);
+        result
+    }
+
+    fn stop_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.stop_qhyccd_live(handle);
+        self.record(
+            start,
+            "StopQHYCCDLive",
+            &[token(&handle)],
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn get_qhyccd_exposure_remaining(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_exposure_remaining(handle);
+        let args = [token(&handle)];
+        self.record(
+            start,
+            "GetQHYCCDExposureRemaining",
+            &args,
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn cancel_qhyccd_exposing(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.cancel_qhyccd_exposing(handle);
+        self.record(
+            start,
+            "CancelQHYCCDExposing",
+            &[token(&handle)],
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn cancel_qhyccd_exposing_and_readout(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.cancel_qhyccd_exposing_and_readout(handle);
+        let args = [token(&handle)];
+        self.record(
+            start,
+            "CancelQHYCCDExposingAndReadout",
+            &args,
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn get_qhyccd_humidity(&self, handle: QhyccdHandle, humidity: &mut f64) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_humidity(handle, humidity);
+        let (args, outputs) = ([token(&handle)], [token(humidity)]);
+        self.record(start, "GetQHYCCDHumidity", &args, token(&result), &outputs);
+        result
+    }
+
+    fn qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.qhyccd_sensor_phase_retrain(handle);
+        let args = [token(&handle)];
+        self.record(
+            start,
+            "QHYCCDSensorPhaseReTrain",
+            &args,
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn get_qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        let start = Instant::now();
+        let result = self.inner.get_qhyccd_sensor_phase_retrain(handle);
+        let args = [token(&handle)];
+        self.record(
+            start,
+            "GetQHYCCDSensorPhaseReTrain",
+            &args,
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn set_qhyccd_enable_live_mode_anti_rbi(&self, handle: QhyccdHandle, value: u32) -> u32 {
+        let start = Instant::now();
+        let result = self
+            .inner
+            .set_qhyccd_enable_live_mode_anti_rbi(handle, value);
+        let args = [token(&handle), token(&value)];
+        self.record(
+            start,
+            "SetQHYCCDEnableLiveModeAntiRBI",
+            &args,
+            token(&result),
+            &[],
+        );
+        result
+    }
+
+    fn set_qhyccd_two_channel_combine_parameter(
+        &self,
+        handle: QhyccdHandle,
+        combine: HdrCombine,
+    ) -> u32 {
+        let start = Instant::now();
+        let result = self
+            .inner
+            .set_qhyccd_two_channel_combine_parameter(handle, combine);
+        let args = [token(&handle), token(&combine)];
+        let function = "SetQHYCCDTwoChannelCombineParameter";
+        self.record(start, function, &args, token(&result), &[]);
+        result
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TraceEntry {
+    line: usize,
+    duration: Duration,
+    function: String,
+    args: Vec<String>,
+    result: String,
+    outputs: Vec<String>,
+}
+
+fn parse_entry(line: usize, text: &str) -> Option<TraceEntry> {
+    let mut tokens = text.split(' ');
+    let _start = tokens.next()?.parse::<u64>().ok()?;
+    let duration = Duration::from_micros(tokens.next()?.parse().ok()?);
+    let function = tokens.next()?.to_string();
+    let mut args = Vec::new();
+    for token in tokens.by_ref() {
+        if token == "=" {
+            break;
+        }
+        args.push(token.to_string());
+    }
+    let result = tokens.next()?.to_string();
+    Some(TraceEntry {
+        line,
+        duration,
+        function,
+        args,
+        result,
+        outputs: tokens.map(str::to_string).collect(),
+    })
+}
+
+#[derive(Debug, Default)]
+struct ReplayState {
+    entries: std::collections::VecDeque<TraceEntry>,
+    mismatches: Vec<String>,
+}
+
+/// Answer SDK calls with the responses of a trace written by `RecordingBackend`
+///
+/// Calls have to arrive in the recorded order with the recorded arguments, a call that does
+/// not match the next entry fails with `QHYCCD_ERROR` and is reported by `mismatches`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use libqhyccd_sys::{get_live_frame, set_backend, ReplayBackend};
+/// let replay = ReplayBackend::open("session.trace").unwrap();
+/// let _backend = set_backend(replay.clone());
+/// // run the code that failed on the recorded camera
+/// assert!(replay.mismatches().is_empty());
+/// assert!(replay.is_finished());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ReplayBackend {
+    state: std::sync::Arc<Mutex<ReplayState>>,
+    recorded_latency: bool,
+}
+
+impl ReplayBackend {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = match File::open(path.as_ref()) {
+            Ok(file) => file,
+            Err(error) => {
+                tracing::error!(error = error.to_string().as_str());
+                return Err(eyre!(error));
+            }
+        };
+        let mut entries = std::collections::VecDeque::new();
+        for (index, text) in BufReader::new(file).lines().enumerate() {
+            let text = text?;
+            let line = index + 1;
+            if line == 1 && text != HEADER {
+                let error = QHYError::ParseTraceError { line };
+                tracing::error!(error = error.to_string().as_str());
+                return Err(eyre!(error));
+            }
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            match parse_entry(line, &text) {
+                Some(entry) => entries.push_back(entry),
+                None => {
+                    let error = QHYError::ParseTraceError { line };
+                    tracing::error!(error = error.to_string().as_str());
+                    return Err(eyre!(error));
+                }
+            }
+        }
+        Ok(Self {
+            state: std::sync::Arc::new(Mutex::new(ReplayState {
+                entries,
+                mismatches: Vec::new(),
+            })),
+            recorded_latency: false,
+        })
+    }
+
+    /// Block every call for as long as it took when it was recorded
+    pub fn with_recorded_latency(self) -> Self {
+        Self {
+            recorded_latency: true,
+            ..self
+        }
+    }
+
+    /// Calls that did not match the trace, in order
+    pub fn mismatches(&self) -> Vec<String> {
+        self.lock().mismatches.clone()
+    }
+
+    /// Whether every recorded call has been replayed
+    pub fn is_finished(&self) -> bool {
+        self.lock().entries.is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ReplayState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Take the next entry if it is a call of `function` with `args` and let `f` turn it into
+    /// the result, `error` is returned for calls that do not match the trace
+    fn replay<T>(
+        &self,
+        function: &str,
+        args: &[String],
+        error: T,
+        f: impl FnOnce(&TraceEntry) -> Option<T>,
+    ) -> T {
+        let entry = {
+            let mut state = self.lock();
+            let mismatch = match state.entries.front() {
+                Some(entry) if entry.function == function && entry.args == args => None,
+                Some(entry) => Some(format!(
+                    "line {}: expected {} {}, got {} {}",
+                    entry.line,
+                    entry.function,
+                    entry.args.join(" "),
+                    function,
+                    args.join(" ")
+                )),
+                None => Some(format!("end of trace: got {} {}", function, args.join(" "))),
+            };
+            if let Some(mismatch) = mismatch {
+                tracing::error!(
+                    mismatch = mismatch.as_str(),
+                    "call does not match the trace"
+                );
+                state.mismatches.push(mismatch);
+                return error;
+            }
+            state.entries.pop_front().unwrap()
+        };
+        if self.recorded_latency {
+            std::thread::sleep(entry.duration);
+        }
+        match f(&entry) {
+            Some(result) => result,
+            None => {
+                let mismatch = format!("line {}: cannot replay {}", entry.line, function);
+                tracing::error!(
+                    mismatch = mismatch.as_str(),
+                    "call does not match the trace"
+                );
+                self.lock().mismatches.push(mismatch);
+                error
+            }
+        }
+    }
+
+    /// Replay a call that only returns a status code or value
+    fn code(&self, function: &str, args: &[String]) -> u32 {
+        self.replay(function, args, bindings::QHYCCD_ERROR, |entry| {
+            parse_u32(&entry.result)
+        })
+    }
+
+    /// Replay a call that writes `N` outputs, parsed by `f`
+    fn outputs<const N: usize>(
+        &self,
+        function: &str,
+        args: &[String],
+        f: impl FnOnce([&str; N]) -> Option<()>,
+    ) -> u32 {
+        self.replay(function, args, bindings::QHYCCD_ERROR, |entry| {
+            let outputs = entry.outputs.iter().map(String::as_str).collect::<Vec<_>>();
+            f(outputs.try_into().ok()?)?;
+            parse_u32(&entry.result)
+        })
+    }
+}
+
+impl CameraBackend for ReplayBackend {
+    fn load(&self) -> Result<(), String> {
+        let error = Err("call does not match the trace".to_string());
+        self.replay("load", &[], error, |entry| match entry.result.as_str() {
+            "1" => Some(Ok(())),
+            _ => Some(Err(parse_string(entry.outputs.first()?)?)),
+        })
+    }
+
+    fn is_available(&self, function: SdkFunction) -> bool {
+        self.replay("is_available", &[token(function.name())], false, |entry| {
+            Some(entry.result == "1")
+        })
+    }
+
+    fn init_qhyccd_resource(&self) -> u32 {
+        self.code("InitQHYCCDResource", &[])
+    }
+
+    fn release_qhyccd_resource(&self) -> u32 {
+        self.code("ReleaseQHYCCDResource", &[])
+    }
+
+    fn scan_qhyccd(&self) -> u32 {
+        self.code("ScanQHYCCD", &[])
+    }
+
+    fn get_qhyccd_sdk_version(
+        &self,
+        year: &mut u32,
+        month: &mut u32,
+        day: &mut u32,
+        subday: &mut u32,
+    ) -> u32 {
+        self.outputs("GetQHYCCDSDKVersion", &[], |[y, m, d, s]| {
+            *year = parse_u32(y)?;
+            *month = parse_u32(m)?;
+            *day = parse_u32(d)?;
+            *subday = parse_u32(s)?;
+            Some(())
+        })
+    }
+
+    fn get_qhyccd_id(&self, index: u32, id: &mut [u8]) -> u32 {
+        self.outputs("GetQHYCCDId", &[token(&index)], |[value]| {
+            write_c_string(id, &parse_c_string(value)?);
+            Some(())
+        })
+    }
+
+    fn open_qhyccd(&self, id: &str) -> QhyccdHandle {
+        let null = QhyccdHandle::new(std::ptr::null_mut());
+        self.replay("OpenQHYCCD", &[token(id)], null, |entry| {
+            let ptr = entry.result.parse::<usize>().ok()?;
+            Some(QhyccdHandle::new(ptr as *mut c_void))
+        })
+    }
+
+    fn close_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        self.code("CloseQHYCCD", &[token(&handle)])
+    }
+
+    fn init_qhyccd(&self, handle: QhyccdHandle) -> u32 {
+        self.code("InitQHYCCD", &[token(&handle)])
+    }
+
+    fn get_qhyccd_fw_version(&self, handle: QhyccdHandle, version: &mut [u8]) -> u32 {
+        self.outputs("GetQHYCCDFWVersion", &[token(&handle)], |[value]| {
+            let bytes = parse_bytes(value)?;
+            version.get_mut(..bytes.len())?.copy_from_slice(&bytes);
+            Some(())
+        })
+    }
+
+    fn get_qhyccd_model(&self, handle: QhyccdHandle, model: &mut [u8]) -> u32 {
+        self.outputs("GetQHYCCDModel", &[token(&handle)], |[value]| {
+            write_c_string(model, &parse_c_string(value)?);
+            Some(())
+        })
+    }
+
+    fn get_qhyccd_type(&self, handle: QhyccdHandle) -> u32 {
+        self.code("GetQHYCCDType", &[token(&handle)])
+    }
+
+    fn is_qhyccd_control_available(&self, handle: QhyccdHandle, feature: CameraFeature) -> u32 {
+        self.code(
+            "IsQHYCCDControlAvailable",
+            &[token(&handle), token(&feature)],
+        )
+    }
+
+    fn set_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: u32) -> u32 {
+        self.code("SetQHYCCDReadMode", &[token(&handle), token(&mode)])
+    }
+
+    fn get_qhyccd_read_mode(&self, handle: QhyccdHandle, mode: &mut u32) -> u32 {
+        self.outputs("GetQHYCCDReadMode", &[token(&handle)], |[value]| {
+            *mode = parse_u32(value)?;
+            Some(())
+        })
+    }
+
+    fn get_qhyccd_number_of_read_modes(&self, handle: QhyccdHandle, modes: &mut u32) -> u32 {
+        self.outputs("GetQHYCCDNumberOfReadModes", &[token(&handle)], |[value]| {
+            *modes = parse_u32(value)?;
+            Some(())
+        })
+    }
+
+    fn get_qhyccd_read_mode_name(&self, handle: QhyccdHandle, mode: u32, name: &mut [u8]) -> u32 {
+        let args = [token(&handle), token(&mode)];
+        self.outputs("GetQHYCCDReadModeName", &args, |[value]| {
+            write_c_string(name, &parse_c_string(value)?);
+            Some(())
+        })
+    }
+
+    fn get_qhyccd_read_mode_resolution(
+        &self,
+        handle: QhyccdHandle,
+        mode: u32,
+        width: &mut u32,
+        height: &mut u32,
+    ) -> u32 {
+        let args = [token(&handle), token(&mode)];
+        self.outputs("GetQHYCCDReadModeResolution", &args, |[w, h]| {
+            *width = parse_u32(w)?;
+            *height = parse_u32(h)?;
+            Some(())
+        })
+    }
+
+    fn set_qhyccd_stream_mode(&self, handle: QhyccdHandle, mode: u8) -> u32 {
+        self.code("SetQHYCCDStreamMode", &[token(&handle), token(&mode)])
+    }
+
+    fn get_qhyccd_chip_info(&self, handle: QhyccdHandle, info: &mut CCDChipInfo) -> u32 {
+        self.outputs("GetQHYCCDChipInfo", &[token(&handle)], |[value]| {
+            *info = parse_chip_info(value)?;
+            Some(())
+        })
+    }
+
+    fn get_qhyccd_overscan_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        self.outputs("GetQHYCCDOverScanArea", &[token(&handle)], |[value]| {
+            *area = parse_area(value)?;
+            Some(())
+        })
+    }
+
+    fn get_qhyccd_effective_area(&self, handle: QhyccdHandle, area: &mut CCDChipArea) -> u32 {
+        self.outputs("GetQHYCCDEffectiveArea", &[token(&handle)], |[value]| {
+            *area = parse_area(value)?;
+            Some(())
+        })
+    }
+
+    fn set_qhyccd_bits_mode(&self, handle: QhyccdHandle, bits: u32) -> u32 {
+        self.code("SetQHYCCDBitsMode", &[token(&handle), token(&bits)])
+    }
+
+    fn set_qhyccd_debayer_on_off(&self, handle: QhyccdHandle, on: bool) -> u32 {
+        self.code("SetQHYCCDDebayerOnOff", &[token(&handle), token(&on)])
+    }
+
+    fn set_qhyccd_bin_mode(&self, handle: QhyccdHandle, bin_x: u32, bin_y: u32) -> u32 {
+        let args = [token(&handle), token(&bin_x), token(&bin_y)];
+        self.code("SetQHYCCDBinMode", &args)
+    }
+
+    fn set_qhyccd_resolution(&self, handle: QhyccdHandle, roi: CCDChipArea) -> u32 {
+        self.code("SetQHYCCDResolution", &[token(&handle), token(&roi)])
+    }
+
+    fn get_qhyccd_current_roi(&self, handle: QhyccdHandle, roi: &mut CCDChipArea) -> u32 {
+        self.outputs("GetQHYCCDCurrentROI", &[token(&handle)], |[value]| {
+            *roi = parse_area(value)?;
+            Some(())
+        })
+    }
+
+    fn set_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature, value: f64) -> u32 {
+        let args = [token(&handle), token(&feature), token(&value)];
+        self.code("SetQHYCCDParam", &args)
+    }
+
+    fn get_qhyccd_param(&self, handle: QhyccdHandle, feature: CameraFeature) -> f64 {
+        let args = [token(&handle), token(&feature)];
+        let error = bindings::QHYCCD_ERROR as f64;
+        self.replay("GetQHYCCDParam", &args, error, |entry| {
+            parse_f64(&entry.result)
+        })
+    }
+
+    fn get_qhyccd_param_min_max_step(
+        &self,
+        handle: QhyccdHandle,
+        feature: CameraFeature,
+        min: &mut f64,
+        max: &mut f64,
+        step: &mut f64,
+    ) -> u32 {
+        let args = [token(&handle), token(&feature)];
+        self.outputs(
+            "GetQHYCCDParamMinMaxStep",
+            &args,
+            |[lower, upper, increment]| {
+                *min = parse_f64(lower)?;
+                *max = parse_f64(upper)?;
+                *step = parse_f64(increment)?;
+                Some(())
+            },
+        )
+    }
+
+    fn get_qhyccd_mem_length(&self, handle: QhyccdHandle) -> u32 {
+        self.code("GetQHYCCDMemLength", &[token(&handle)])
+    }
+
+    fn exp_qhyccd_single_frame(&self, handle: QhyccdHandle) -> u32 {
+        self.code("ExpQHYCCDSingleFrame", &[token(&handle)])
+    }
+
+    fn get_qhyccd_single_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        self.outputs("GetQHYCCDSingleFrame", &[token(&handle)], |[value]| {
+            parse_frame(value, image)
+        })
+    }
+
+    fn begin_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        self.code("BeginQHYCCDLive", &[token(&handle)])
+    }
+
+    fn get_qhyccd_live_frame(&self, handle: QhyccdHandle, image: &mut ImageData) -> u32 {
+        self.outputs("GetQHYCCDLiveFrame", &[token(&handle)], |[value]| {
+            parse_frame(value, image)
+        })
+    }
+
+    fn stop_qhyccd_live(&self, handle: QhyccdHandle) -> u32 {
+        self.code("StopQHYCCDLive", &[token(&handle)])
+    }
+
+    fn get_qhyccd_exposure_remaining(&self, handle: QhyccdHandle) -> u32 {
+        self.code("GetQHYCCDExposureRemaining", &[token(&handle)])
+    }
+
+    fn cancel_qhyccd_exposing(&self, handle: QhyccdHandle) -> u32 {
+        self.code("CancelQHYCCDExposing", &[token(&handle)])
+    }
+
+    fn cancel_qhyccd_exposing_and_readout(&self, handle: QhyccdHandle) -> u32 {
+        self.code("CancelQHYCCDExposingAndReadout", &[token(&handle)])
+    }
+
+    fn get_qhyccd_humidity(&self, handle: QhyccdHandle, humidity: &mut f64) -> u32 {
+        self.outputs("GetQHYCCDHumidity", &[token(&handle)], |[value]| {
+            *humidity = parse_f64(value)?;
+            Some(())
+        })
+    }
+
+    fn qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        self.code("QHYCCDSensorPhaseReTrain", &[token(&handle)])
+    }
+
+    fn get_qhyccd_sensor_phase_retrain(&self, handle: QhyccdHandle) -> u32 {
+        self.code("GetQHYCCDSensorPhaseReTrain", &[token(&handle)])
+    }
+
+    fn set_qhyccd_enable_live_mode_anti_rbi(&self, handle: QhyccdHandle, value: u32) -> u32 {
+        let args = [token(&handle), token(&value)];
+        self.code("SetQHYCCDEnableLiveModeAntiRBI", &args)
+    }
+
+    fn set_qhyccd_two_channel_combine_parameter(
+        &self,
+        handle: QhyccdHandle,
+        combine: HdrCombine,
+    ) -> u32 {
+        let args = [token(&handle), token(&combine)];
+        self.code("SetQHYCCDTwoChannelCombineParameter", &args)
+    }
+}
@@ -0,0 +1,30 @@
+//! Fixture shared by the tests running the safe API against a test backend
+// every test executable includes this module and uses a different part of it
+#![allow(dead_code)]
+
+use libqhyccd_sys::{
+    get_camera_id, init_sdk, open_camera, scan_qhyccd, set_backend, BackendGuard, CameraBackend,
+    QhyccdHandle,
+};
+
+/// Install `backend`, initialize the SDK and scan for cameras
+///
+/// The backend stays installed until the returned guard is dropped, also when the test fails.
+/// Tests installing a backend wait for each other.
+pub fn install(backend: impl CameraBackend + 'static) -> BackendGuard {
+    let guard = set_backend(backend);
+    init_sdk().unwrap();
+    scan_qhyccd().unwrap();
+    guard
+}
+
+/// Open the camera at `index` of the scan done by `install`
+pub fn open(index: u32) -> QhyccdHandle {
+    open_camera(get_camera_id(index).unwrap()).unwrap()
+}
+
+/// `install` the backend and open its first camera
+pub fn open_first_camera(backend: impl CameraBackend + 'static) -> (BackendGuard, QhyccdHandle) {
+    let guard = install(backend);
+    (guard, open(0))
+}
@@ -0,0 +1,172 @@
+//! Runs the wrappers against the simulated SDK built by the fake-qhyccd workspace member
+//!
+//! `cargo test --workspace --features libqhyccd-sys/runtime-link`
+//!
+//! The fake library is not a dependency of this crate, the tests build it with
+//! `cargo build -p fake-qhyccd` into the same target directory before loading it.
+#![cfg(feature = "runtime-link")]
+use std::{
+    env,
+    process::Command,
+    sync::{Mutex, Once},
+    time::Duration,
+};
+
+use libqhyccd_sys::{
+    close_camera, get_camera_id, get_effective_area, get_image_size, get_overscan_area,
+    get_sdk_version, get_single_frame, init_camera, init_sdk, open_camera, readout_modes,
+    release_sdk, scan_qhyccd, set_bin_mode, set_bit_depth, set_exposure, set_readout_mode, set_roi,
+    set_stream_mode, start_single_frame_exposure, BitDepth, CCDChipArea, CameraStreamMode,
+    FrameNumber, LiveSession, QhyccdHandle,
+};
+
+// the SDK state is global, run one test at a time
+static SDK: Mutex<()> = Mutex::new(());
+
+static BUILD_FAKE: Once = Once::new();
+
+/// Build fake-qhyccd with the profile of this test executable and point `QHYCCD_LIBRARY` at it
+///
+/// The variable is set once, before the first test loads the SDK, so no load reads it while it
+/// changes.
+fn build_fake_library() {
+    BUILD_FAKE.call_once(|| {
+        let mut cargo = Command::new(env!("CARGO"));
+        cargo.args(["build", "-p", "fake-qhyccd"]);
+        if !cfg!(debug_assertions) {
+            cargo.arg("--release");
+        }
+        let status = cargo.status().expect("could not run cargo");
+        assert!(status.success(), "`cargo build -p fake-qhyccd` failed");
+
+        // test executables live in target/<profile>/deps, the fake library in target/<profile>
+        let exe = env::current_exe().unwrap();
+        let library = exe
+            .parent()
+            .and_then(|deps| deps.parent())
+            .unwrap()
+            .join(libloading::library_filename("qhyccd"));
+        assert!(
+            library.exists(),
+            "{} not found after building fake-qhyccd",
+            library.display()
+        );
+        env::set_var("QHYCCD_LIBRARY", library);
+    });
+}
+
+fn open_fake_camera() -> QhyccdHandle {
+    build_fake_library();
+    init_sdk().expect("init_sdk failed");
+    assert_eq!(scan_qhyccd().expect("scan_qhyccd failed"), 1);
+    let id = get_camera_id(0).expect("get_camera_id failed");
+    open_camera(id).expect("open_camera failed")
+}
+
+fn close_fake_camera(camera: QhyccdHandle) {
+    close_camera(camera).expect("close_camera failed");
+    release_sdk().expect("release_sdk failed");
+}
+
+#[test]
+fn sdk_version() {
+    let _sdk = SDK.lock().unwrap_or_else(|e| e.into_inner());
+    let camera = open_fake_camera();
+    assert_eq!(get_sdk_version().unwrap().to_string(), "23.9.6.14");
+    close_fake_camera(camera);
+}
+
+#[test]
+fn single_frame() {
+    let _sdk = SDK.lock().unwrap_or_else(|e| e.into_inner());
+    let camera = open_fake_camera();
+    set_stream_mode(camera, CameraStreamMode::SingleFrameMode).unwrap();
+    init_camera(camera).unwrap();
+
+    let modes = readout_modes(camera).unwrap();
+    assert_eq!(modes.len(), 2);
+    assert_eq!(modes[1].name, "HIGH GAIN MODE");
+
+    let overscan = get_overscan_area(camera).unwrap();
+    let effective_area = get_effective_area(camera).unwrap();
+    assert_eq!(overscan.width, 16);
+    assert_eq!(effective_area.start_x, 16);
+
+    set_exposure(camera, Duration::from_millis(2)).unwrap();
+    set_bit_depth(camera, BitDepth::Sixteen).unwrap();
+    set_roi(camera, effective_area).unwrap();
+    start_single_frame_exposure(camera).unwrap();
+    let buffer_size = get_image_size(camera).unwrap();
+    let image = get_single_frame(camera, buffer_size).unwrap();
+    assert_eq!(image.width, effective_area.width);
+    assert_eq!(image.height, effective_area.height);
+    assert_eq!(image.bits_per_pixel, 16);
+    assert_eq!(image.channels, 1);
+
+    close_fake_camera(camera);
+}
+
+#[test]
+fn rejected_settings_are_not_applied() {
+    let _sdk = SDK.lock().unwrap_or_else(|e| e.into_inner());
+    let camera = open_fake_camera();
+    set_stream_mode(camera, CameraStreamMode::SingleFrameMode).unwrap();
+    init_camera(camera).unwrap();
+
+    assert!(set_bin_mode(camera, 0, 0).is_err());
+    assert!(set_bin_mode(camera, 3, 3).is_err());
+    assert!(set_readout_mode(camera, 7).is_err());
+    let too_wide = CCDChipArea {
+        start_x: u32::MAX,
+        start_y: 0,
+        width: 2,
+        height: 1,
+    };
+    assert!(set_roi(camera, too_wide).is_err());
+
+    set_exposure(camera, Duration::from_millis(2)).unwrap();
+    start_single_frame_exposure(camera).unwrap();
+    let buffer_size = get_image_size(camera).unwrap();
+    let image = get_single_frame(camera, buffer_size).unwrap();
+    assert_eq!((image.width, image.height), (656, 480));
+    assert_eq!(image.data.len(), buffer_size);
+
+    close_fake_camera(camera);
+}
+
+#[test]
+fn live_frames_are_numbered() {
+    let _sdk = SDK.lock().unwrap_or_else(|e| e.into_inner());
+    let camera = open_fake_camera();
+    set_stream_mode(camera, CameraStreamMode::LiveMode).unwrap();
+    init_camera(camera).unwrap();
+    set_roi(
+        camera,
+        CCDChipArea {
+            start_x: 0,
+            start_y: 0,
+            width: 64,
+            height: 64,
+        },
+    )
+    .unwrap();
+    libqhyccd_sys::begin_live(camera).unwrap();
+
+    let buffer_size = get_image_size(camera).unwrap();
+    let mut session = LiveSession::new(camera, buffer_size);
+    let numbers = (0..3)
+        .map(|_| session.next_frame().unwrap().frame_number)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        numbers,
+        vec![
+            FrameNumber::Hardware(1),
+            FrameNumber::Hardware(2),
+            FrameNumber::Hardware(3)
+        ]
+    );
+    assert_eq!(session.statistics().dropped, 0);
+
+    libqhyccd_sys::end_live(camera).unwrap();
+    close_fake_camera(camera);
+}
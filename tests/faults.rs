@@ -0,0 +1,77 @@
+//! Exercises error paths of the safe API with `FaultInjector` around `MockBackend`
+use std::{thread, time::Duration};
+
+use libqhyccd_sys::{
+    begin_live, get_image_size, get_live_frame, get_model, scan_qhyccd, set_parameter,
+    CameraFeature, Fault, FaultInjector, ImageData, MockBackend, SdkFunction,
+};
+
+mod common;
+
+#[test]
+fn parameter_fails_for_one_feature() {
+    let faults = FaultInjector::wrap(MockBackend::new()).with_fault(Fault::FailParameter {
+        feature: CameraFeature::ControlGain,
+        code: 7,
+    });
+    let (_backend, camera) = common::open_first_camera(faults.clone());
+
+    let error = set_parameter(camera, CameraFeature::ControlGain, 10.0).unwrap_err();
+    assert!(error.to_string().contains('7'), "{}", error);
+    set_parameter(camera, CameraFeature::ControlOffset, 10.0).unwrap();
+    assert_eq!(faults.injected(), [SdkFunction::SetQHYCCDParam]);
+}
+
+#[test]
+fn disconnect_after_timeout() {
+    let faults = FaultInjector::wrap(MockBackend::new());
+    let (_backend, camera) = common::open_first_camera(faults.clone());
+
+    faults.inject(Fault::DisconnectAfter(Duration::from_millis(20)));
+    assert!(get_model(camera).is_ok());
+    thread::sleep(Duration::from_millis(30));
+    assert!(get_model(camera).is_err());
+    assert_eq!(scan_qhyccd().unwrap(), 0);
+
+    faults.reconnect();
+    assert_eq!(scan_qhyccd().unwrap(), 1);
+    assert!(get_model(camera).is_ok());
+}
+
+#[test]
+fn truncated_live_frame() {
+    let mock = MockBackend::new();
+    let frame = ImageData {
+        data: vec![0xAB; 4 * 4 * 2],
+        width: 4,
+        height: 4,
+        bits_per_pixel: 16,
+        channels: 1,
+    };
+    mock.push_frame(frame.clone());
+    mock.push_frame(frame.clone());
+    let faults = FaultInjector::wrap(mock)
+        .with_fault(Fault::TruncateFrame { nth: 2, rows: 1 })
+        .with_fault(Fault::FailNth {
+            function: SdkFunction::GetQHYCCDLiveFrame,
+            nth: 1,
+            code: 0xFFFF_FFFF,
+        });
+    let (_backend, camera) = common::open_first_camera(faults.clone());
+
+    begin_live(camera).unwrap();
+    let size = get_image_size(camera).unwrap();
+    assert!(get_live_frame(camera, size).is_err());
+    assert_eq!(get_live_frame(camera, size).unwrap().data[..32], frame.data);
+    let truncated = get_live_frame(camera, size).unwrap();
+    assert_eq!(truncated.height, 4);
+    assert!(truncated.data[..8].iter().all(|&byte| byte == 0xAB));
+    assert!(truncated.data[8..32].iter().all(|&byte| byte == 0));
+    assert_eq!(
+        faults.injected(),
+        [
+            SdkFunction::GetQHYCCDLiveFrame,
+            SdkFunction::GetQHYCCDLiveFrame
+        ]
+    );
+}
@@ -0,0 +1,87 @@
+//! Checks which SDK calls the crate lets run concurrently, using `MockBackend` latencies
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use libqhyccd_sys::{
+    get_image_size, get_single_frame, scan_qhyccd, stop_exposure, BackendGuard, MockBackend,
+    QhyccdHandle, SdkFunction,
+};
+
+mod common;
+
+const LATENCY: Duration = Duration::from_millis(100);
+
+fn open_cameras() -> (BackendGuard, QhyccdHandle, QhyccdHandle) {
+    let mock = MockBackend::new().with_cameras(&["QHY178M-mock0", "QHY178M-mock1"]);
+    mock.set_latency(SdkFunction::GetQHYCCDSingleFrame, LATENCY);
+    let backend = common::install(mock);
+    (backend, common::open(0), common::open(1))
+}
+
+/// Run `f` on two threads at once and return how long both took
+fn concurrently(f: impl Fn(usize) + Sync) -> Duration {
+    let start = Instant::now();
+    thread::scope(|scope| {
+        scope.spawn(|| f(0));
+        scope.spawn(|| f(1));
+    });
+    start.elapsed()
+}
+
+#[test]
+fn calls_on_one_camera_are_serialized() {
+    let (_backend, camera, _) = open_cameras();
+    let size = get_image_size(camera).unwrap();
+
+    let elapsed = concurrently(|_| {
+        get_single_frame(camera, size).unwrap();
+    });
+    assert!(elapsed >= 2 * LATENCY, "{:?}", elapsed);
+}
+
+#[test]
+fn calls_on_different_cameras_run_concurrently() {
+    let (_backend, first, second) = open_cameras();
+    let size = get_image_size(first).unwrap();
+
+    let elapsed = concurrently(|index| {
+        let camera = [first, second][index];
+        get_single_frame(camera, size).unwrap();
+    });
+    assert!(elapsed < 2 * LATENCY, "{:?}", elapsed);
+}
+
+#[test]
+fn sdk_wide_calls_wait_and_stopping_does_not() {
+    let (_backend, camera, _) = open_cameras();
+    let size = get_image_size(camera).unwrap();
+
+    thread::scope(|scope| {
+        scope.spawn(|| get_single_frame(camera, size).unwrap());
+        thread::sleep(LATENCY / 4);
+        let start = Instant::now();
+        stop_exposure(camera).unwrap();
+        assert!(start.elapsed() < LATENCY / 2, "{:?}", start.elapsed());
+        scan_qhyccd().unwrap();
+        assert!(start.elapsed() >= LATENCY / 2, "{:?}", start.elapsed());
+    });
+}
+
+#[test]
+fn stopping_passes_a_waiting_sdk_wide_call() {
+    let (_backend, camera, _) = open_cameras();
+    let size = get_image_size(camera).unwrap();
+
+    thread::scope(|scope| {
+        scope.spawn(|| get_single_frame(camera, size).unwrap());
+        thread::sleep(LATENCY / 4);
+        // waits for the exposure to finish
+        scope.spawn(|| scan_qhyccd().unwrap());
+        thread::sleep(LATENCY / 4);
+        let start = Instant::now();
+        stop_exposure(camera).unwrap();
+        assert!(start.elapsed() < LATENCY / 4, "{:?}", start.elapsed());
+    });
+}
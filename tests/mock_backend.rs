@@ -0,0 +1,679 @@
+//! Runs the safe API against `MockBackend`
+use std::time::{Duration, Instant};
+
+use libqhyccd_sys::{
+    begin_live, capture_single_frame, centered_roi, combine_hdr_channels, exposure, get_binning,
+    get_gain_db, get_humidity, get_image_size, get_live_frame, get_readout_mode, get_roi,
+    get_sensor_curves, get_sensor_health, init_sdk, scan_qhyccd, sensor_phase_retrain, set_backend,
+    set_bin_mode, set_binning, set_bit_depth, set_exposure, set_gain, set_parameter_checked,
+    set_readout_mode_by_name, set_roi, white_balance_from_image, BackendGuard, Binning, BitDepth,
+    CCDChipArea, CCDChipInfo, CameraFeature, CyclePumpPolicy, FrameNumber, Gain, HdrCombine,
+    ImageData, LiveSession, LiveStatistics, MockBackend, Overscan, QHYError, QhyccdHandle,
+    RbiMitigation, ReadoutMode, RoiConstraints, SDKVersion, SdkFunction, SensorCurvePoint,
+    SensorCurves, SensorHealthMonitor, SingleFrameOptions,
+};
+
+mod common;
+
+fn open_mock_camera(mock: &MockBackend) -> (BackendGuard, QhyccdHandle) {
+    let opened = common::open_first_camera(mock.clone());
+    mock.clear_calls();
+    opened
+}
+
+#[test]
+fn exposure_is_set_in_microseconds() {
+    let mock = MockBackend::new();
+    let (_backend, camera) = open_mock_camera(&mock);
+
+    set_exposure(camera, Duration::from_millis(20)).unwrap();
+    assert_eq!(
+        mock.parameter(CameraFeature::ControlExposure),
+        Some(20_000.0)
+    );
+    assert_eq!(mock.calls(), ["GetQHYCCDParamMinMaxStep", "SetQHYCCDParam"]);
+    assert_eq!(exposure(camera).unwrap(), Duration::from_millis(20));
+
+    assert!(set_parameter_checked(camera, CameraFeature::ControlGain, 101.0).is_err());
+    assert_eq!(mock.parameter(CameraFeature::ControlGain), Some(0.0));
+}
+
+#[test]
+fn scripted_live_frame_errors() {
+    let mock = MockBackend::new();
+    let (_backend, camera) = open_mock_camera(&mock);
+    let frame = ImageData {
+        data: vec![1, 2, 3, 4],
+        width: 2,
+        height: 1,
+        bits_per_pixel: 16,
+        channels: 1,
+    };
+    mock.push_frame(frame.clone());
+    mock.return_codes(SdkFunction::GetQHYCCDLiveFrame, [0xFFFF_FFFF, 0xFFFF_FFFF]);
+
+    begin_live(camera).unwrap();
+    let size = get_image_size(camera).unwrap();
+    assert!(get_live_frame(camera, size).is_err());
+    assert!(get_live_frame(camera, size).is_err());
+    let image = get_live_frame(camera, size).unwrap();
+    assert_eq!(image.width, 2);
+    assert_eq!(image.data[..4], frame.data);
+}
+
+#[test]
+fn latency_and_sdk_version() {
+    let mock = MockBackend::new().with_sdk_version(SDKVersion {
+        year: 21,
+        month: 1,
+        day: 1,
+        subday: 0,
+    });
+    let _backend = set_backend(mock.clone());
+    assert!(init_sdk().is_err());
+    assert_eq!(mock.calls(), ["GetQHYCCDSDKVersion"]);
+
+    mock.set_latency(SdkFunction::ScanQHYCCD, Duration::from_millis(50));
+    let start = Instant::now();
+    assert_eq!(scan_qhyccd().unwrap(), 1);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn functions_newer_than_the_sdk_fail() {
+    let mock = MockBackend::new()
+        .with_sdk_version(SDKVersion {
+            year: 22,
+            month: 1,
+            day: 1,
+            subday: 0,
+        })
+        .without_function(SdkFunction::GetQHYCCDHumidity);
+    let (_backend, camera) = open_mock_camera(&mock);
+
+    let error = sensor_phase_retrain(camera).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<QHYError>(),
+        Some(QHYError::UnsupportedBySdk { required, .. })
+            if *required == SdkFunction::QHYCCDSensorPhaseReTrain.introduced_in()
+    ));
+    let error = get_humidity(camera).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<QHYError>(),
+        Some(QHYError::SdkFunctionMissing {
+            function: SdkFunction::GetQHYCCDHumidity,
+            ..
+        })
+    ));
+    assert!(mock.calls().is_empty());
+}
+
+#[test]
+fn roi_past_the_sensor_is_rejected() {
+    let mock = MockBackend::new();
+    let (_backend, camera) = open_mock_camera(&mock);
+    let roi = CCDChipArea {
+        start_x: u32::MAX,
+        start_y: 0,
+        width: 2,
+        height: 1,
+    };
+    assert!(set_roi(camera, roi).is_err());
+}
+
+#[test]
+fn overscan_is_cropped_from_binned_sub_frames() {
+    let area = |start_x, width| CCDChipArea {
+        start_x,
+        start_y: 0,
+        width,
+        height: 4,
+    };
+    let mock = MockBackend::new()
+        .with_chip_info(CCDChipInfo {
+            image_width: 8,
+            image_height: 4,
+            bits_per_pixel: 8,
+            ..Default::default()
+        })
+        .with_overscan(area(0, 2), area(2, 6));
+    let (_backend, camera) = open_mock_camera(&mock);
+    set_bin_mode(camera, 2, 2).unwrap();
+    set_roi(
+        camera,
+        CCDChipArea {
+            height: 2,
+            ..area(0, 3)
+        },
+    )
+    .unwrap();
+    mock.push_frame(ImageData {
+        data: vec![0, 1, 2, 3, 4, 5],
+        width: 3,
+        height: 2,
+        bits_per_pixel: 8,
+        channels: 1,
+    });
+    let options = SingleFrameOptions {
+        overscan: Overscan::RemoveKeepStrip,
+        ..Default::default()
+    };
+    let frame = capture_single_frame(camera, &options).unwrap();
+    assert_eq!((frame.image.width, frame.image.height), (2, 2));
+    assert_eq!(frame.image.data, [1, 2, 4, 5]);
+    assert_eq!(frame.overscan.unwrap().data, [0, 3]);
+}
+
+#[test]
+fn crop_handles_empty_and_32_bit_images() {
+    let empty = ImageData {
+        bits_per_pixel: 16,
+        channels: 1,
+        ..Default::default()
+    };
+    assert!(empty.crop(CCDChipArea::default()).unwrap().data.is_empty());
+    let image = ImageData {
+        data: (0..8).collect(),
+        width: 2,
+        height: 1,
+        bits_per_pixel: 32,
+        channels: 1,
+    };
+    let area = CCDChipArea {
+        start_x: 1,
+        start_y: 0,
+        width: 1,
+        height: 1,
+    };
+    assert_eq!(image.crop(area).unwrap().data, [4, 5, 6, 7]);
+}
+
+#[test]
+fn mean_of_32_bit_images() {
+    let image = ImageData {
+        data: [70_000u32, 130_000]
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect(),
+        width: 2,
+        height: 1,
+        bits_per_pixel: 32,
+        channels: 1,
+    };
+    assert_eq!(image.mean(), 100_000.0);
+}
+
+#[test]
+fn roi_alignment_follows_the_model() {
+    let mock = MockBackend::new().with_model("QHY178M");
+    let (_backend, camera) = open_mock_camera(&mock);
+    let roi = centered_roi(camera, 10, 10).unwrap();
+    assert_eq!((roi.width, roi.height), (8, 8));
+
+    let area = CCDChipArea {
+        start_x: 0,
+        start_y: 0,
+        width: 3,
+        height: 3,
+    };
+    let constraints = RoiConstraints::new(8, 8, area).with_alignment(0);
+    assert_eq!(constraints.alignment(), 1);
+    assert!(constraints.validate(area).is_ok());
+}
+
+#[test]
+fn binning_rescales_from_non_square_bin_modes() {
+    let mock = MockBackend::new();
+    let (_backend, camera) = open_mock_camera(&mock);
+    set_bin_mode(camera, 2, 2).unwrap();
+    set_bin_mode(camera, 1, 2).unwrap();
+    assert_eq!(get_binning(camera), Binning::Bin1x1);
+    let roi = CCDChipArea {
+        start_x: 4,
+        start_y: 2,
+        width: 8,
+        height: 4,
+    };
+    set_roi(camera, roi).unwrap();
+
+    set_binning(camera, Binning::Bin2x2).unwrap();
+    assert_eq!(
+        get_roi(camera).unwrap(),
+        CCDChipArea {
+            start_x: 2,
+            start_y: 2,
+            width: 4,
+            height: 4,
+        }
+    );
+}
+
+#[test]
+fn failed_ddr_query_keeps_the_frame() {
+    let mock = MockBackend::new();
+    let (_backend, camera) = open_mock_camera(&mock);
+    mock.push_frame(ImageData {
+        data: vec![1, 2],
+        width: 1,
+        height: 1,
+        bits_per_pixel: 16,
+        channels: 1,
+    });
+    begin_live(camera).unwrap();
+    let mut session =
+        LiveSession::new(camera, get_image_size(camera).unwrap()).warn_on_ddr_fill(0.5);
+    assert_eq!(session.next_frame().unwrap().image.data[..2], [1, 2]);
+}
+
+#[test]
+fn health_monitor_stops_without_waiting_for_the_interval() {
+    let mock = MockBackend::new();
+    let (_backend, camera) = open_mock_camera(&mock);
+    let (monitor, events) = SensorHealthMonitor::start(camera, Duration::from_secs(60));
+    events.recv().unwrap();
+    let started = Instant::now();
+    drop(monitor);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn gain_in_db_is_converted_without_camera_support() {
+    let mock = MockBackend::new();
+    let (backend, camera) = open_mock_camera(&mock);
+    assert!(set_gain(camera, Gain::Decibels(12.0)).is_err());
+    drop(backend);
+
+    let mock =
+        MockBackend::new().with_parameter_range(CameraFeature::ControlGaindB, 0.0, 30.0, 0.1);
+    let (_backend, camera) = open_mock_camera(&mock);
+    set_gain(camera, Gain::Decibels(12.1)).unwrap();
+    assert_eq!(mock.parameter(CameraFeature::ControlGain), Some(40.0));
+    assert_eq!(get_gain_db(camera).unwrap(), Gain::Decibels(12.0));
+}
+
+#[test]
+fn white_balance_rejects_an_inverted_range() {
+    let mock = MockBackend::new()
+        .with_parameter_range(CameraFeature::ControlWbr, 255.0, 0.0, 1.0)
+        .with_parameter_range(CameraFeature::ControlWbg, 0.0, 255.0, 1.0)
+        .with_parameter_range(CameraFeature::ControlWbb, 0.0, 255.0, 1.0);
+    let (_backend, camera) = open_mock_camera(&mock);
+    let image = ImageData {
+        data: vec![10, 20, 30],
+        width: 1,
+        height: 1,
+        bits_per_pixel: 8,
+        channels: 3,
+    };
+    let error = white_balance_from_image(camera, &image).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<QHYError>(),
+        Some(QHYError::InvalidParameterRangeError { .. })
+    ));
+}
+
+#[test]
+fn exposure_is_rounded_to_the_camera_step() {
+    let mock = MockBackend::new().with_parameter_range(
+        CameraFeature::ControlExposure,
+        10.0,
+        1_000.0,
+        20.0,
+    );
+    let (_backend, camera) = open_mock_camera(&mock);
+    set_exposure(camera, Duration::from_micros(105)).unwrap();
+    assert_eq!(mock.parameter(CameraFeature::ControlExposure), Some(110.0));
+}
+
+#[test]
+fn rbi_flush_frames_and_pre_flash() {
+    let options = |pre_flash| SingleFrameOptions {
+        rbi_mitigation: Some(RbiMitigation {
+            pre_flash,
+            flush_frames: 2,
+        }),
+        ..Default::default()
+    };
+    let exposures = |mock: &MockBackend| {
+        mock.calls()
+            .into_iter()
+            .filter(|call| *call == "ExpQHYCCDSingleFrame")
+            .count()
+    };
+
+    let mock = MockBackend::new();
+    let (backend, camera) = open_mock_camera(&mock);
+    capture_single_frame(camera, &options(false)).unwrap();
+    assert_eq!(exposures(&mock), 3);
+    assert_eq!(mock.parameter(CameraFeature::ControlRemoveRbi), None);
+    let error = capture_single_frame(camera, &options(true)).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<QHYError>(),
+        Some(QHYError::UnsupportedFeatureError {
+            feature: CameraFeature::ControlRemoveRbi
+        })
+    ));
+    drop(backend);
+
+    let mock = MockBackend::new().with_features(&[
+        CameraFeature::CamSingleFrameMode,
+        CameraFeature::ControlRemoveRbi,
+    ]);
+    let (_backend, camera) = open_mock_camera(&mock);
+    capture_single_frame(camera, &options(true)).unwrap();
+    assert_eq!(exposures(&mock), 3);
+    assert_eq!(mock.parameter(CameraFeature::ControlRemoveRbi), Some(1.0));
+}
+
+/// Frame numbers and statistics of a live session reading `counters` from the hardware frame
+/// counter, or three frames without a hardware counter if `counters` is `None`
+fn live_session(counters: Option<&[u32]>) -> (Vec<FrameNumber>, LiveStatistics) {
+    let mut features = vec![CameraFeature::CamLiveVideoMode];
+    if counters.is_some() {
+        features.push(CameraFeature::HasHardwareFrameCounter);
+    }
+    let mock = MockBackend::new().with_features(&features);
+    let (_backend, camera) = open_mock_camera(&mock);
+    begin_live(camera).unwrap();
+    let mut session = LiveSession::new(camera, get_image_size(camera).unwrap());
+    let numbers = match counters {
+        Some(counters) => counters
+            .iter()
+            .map(|&counter| {
+                mock.set_parameter(CameraFeature::HasHardwareFrameCounter, counter as f64);
+                session.next_frame().unwrap().frame_number
+            })
+            .collect(),
+        None => (0..3)
+            .map(|_| session.next_frame().unwrap().frame_number)
+            .collect(),
+    };
+    (numbers, session.statistics())
+}
+
+#[test]
+fn hardware_frame_counter_statistics() {
+    let statistics = |delivered, dropped, duplicated| LiveStatistics {
+        delivered,
+        dropped,
+        duplicated,
+    };
+    let cases: [(&[u32], LiveStatistics); 6] = [
+        (&[1, 2, 3], statistics(3, 0, 0)),
+        (&[1, 2, 5], statistics(3, 2, 0)),
+        (&[1, 2, 2], statistics(2, 0, 1)),
+        (&[5, 3], statistics(1, 0, 1)),
+        // u32::MAX is QHYCCD_ERROR, so the counter is never read with that value
+        (&[u32::MAX - 2, u32::MAX - 1, 0, 1], statistics(4, 1, 0)),
+        (&[u32::MAX - 1, 2], statistics(2, 3, 0)),
+    ];
+    for (counters, expected) in cases {
+        let (numbers, statistics) = live_session(Some(counters));
+        assert_eq!(statistics, expected, "{:?}", counters);
+        let hardware = counters
+            .iter()
+            .map(|&counter| FrameNumber::Hardware(counter))
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, hardware);
+    }
+}
+
+#[test]
+fn frames_are_numbered_on_the_host_without_a_hardware_counter() {
+    let (numbers, statistics) = live_session(None);
+    assert_eq!(
+        numbers,
+        [
+            FrameNumber::Host(0),
+            FrameNumber::Host(1),
+            FrameNumber::Host(2)
+        ]
+    );
+    assert_eq!(
+        statistics,
+        LiveStatistics {
+            delivered: 3,
+            dropped: 0,
+            duplicated: 0,
+        }
+    );
+}
+
+fn sensor_curves(points: &[(f64, f64, f64)]) -> SensorCurves {
+    SensorCurves {
+        readout_mode: 0,
+        points: points
+            .iter()
+            .map(|&(gain, system_gain, read_noise)| SensorCurvePoint {
+                gain,
+                system_gain,
+                full_well: 50_000.0,
+                read_noise,
+            })
+            .collect(),
+        gain_step: 1.0,
+    }
+}
+
+#[test]
+fn unity_gain_is_interpolated_and_rounded_to_the_step() {
+    let crossing = sensor_curves(&[(0.0, 2.0, 3.0), (10.0, 1.7, 2.0), (20.0, 0.5, 1.0)]);
+    assert_eq!(crossing.unity_gain(), Some(16.0));
+    let no_crossing = sensor_curves(&[(0.0, 3.0, 3.0), (10.0, 2.0, 2.0)]);
+    assert_eq!(no_crossing.unity_gain(), None);
+    assert_eq!(sensor_curves(&[]).unity_gain(), None);
+}
+
+#[test]
+fn lowest_read_noise_gain_selects_the_first_minimum() {
+    let falling = sensor_curves(&[(0.0, 2.0, 3.0), (10.0, 1.0, 1.5), (20.0, 0.5, 2.0)]);
+    assert_eq!(falling.lowest_read_noise_gain(), Some(10.0));
+    let flat = sensor_curves(&[(0.0, 2.0, 2.0), (10.0, 1.0, 2.0)]);
+    assert_eq!(flat.lowest_read_noise_gain(), Some(0.0));
+    assert_eq!(sensor_curves(&[]).lowest_read_noise_gain(), None);
+}
+
+#[test]
+fn sensor_curves_sample_settable_gains() {
+    let mock = MockBackend::new();
+    for feature in [
+        CameraFeature::CamCurveSystemGain,
+        CameraFeature::CamCurveFullWell,
+        CameraFeature::CamCurveReadoutNoise,
+    ] {
+        mock.set_parameter(feature, 1.0);
+    }
+    let (_backend, camera) = open_mock_camera(&mock);
+    mock.set_parameter(CameraFeature::ControlGain, 30.0);
+
+    let curves = get_sensor_curves(camera, 0, 4).unwrap();
+    let gains = curves
+        .points
+        .iter()
+        .map(|point| point.gain)
+        .collect::<Vec<_>>();
+    assert_eq!(gains, [0.0, 33.0, 67.0, 100.0]);
+    assert_eq!(curves.gain_step, 1.0);
+    // more samples than gain steps
+    assert_eq!(get_sensor_curves(camera, 0, 500).unwrap().points.len(), 101);
+    assert_eq!(mock.parameter(CameraFeature::ControlGain), Some(30.0));
+}
+
+#[test]
+fn sensor_health_without_the_retrain_status_function() {
+    let mock = MockBackend::new()
+        .with_sdk_version(SDKVersion {
+            year: 22,
+            month: 1,
+            day: 1,
+            subday: 0,
+        })
+        .with_features(&[
+            CameraFeature::CamSensorPhaseReTrain,
+            CameraFeature::CamWatchDogFpga,
+        ]);
+    mock.set_parameter(CameraFeature::CamWatchDogFpga, 1.0);
+    let (_backend, camera) = open_mock_camera(&mock);
+    let health = get_sensor_health(camera).unwrap();
+    assert!(!health.phase_retrain_pending);
+    assert!(health.fpga_watchdog_triggered);
+    assert!(!mock.calls().contains(&"GetQHYCCDSensorPhaseReTrain"));
+}
+
+#[test]
+fn binning_without_the_current_roi_function_uses_the_effective_area() {
+    let mock = MockBackend::new().without_function(SdkFunction::GetQHYCCDCurrentROI);
+    let (_backend, camera) = open_mock_camera(&mock);
+    set_binning(camera, Binning::Bin2x2).unwrap();
+    assert!(!mock.calls().contains(&"GetQHYCCDCurrentROI"));
+    assert_eq!(get_binning(camera), Binning::Bin2x2);
+}
+
+#[test]
+fn failed_binning_restores_the_bin_mode_and_sub_frame() {
+    let mock = MockBackend::new();
+    let (_backend, camera) = open_mock_camera(&mock);
+    let roi = CCDChipArea {
+        start_x: 10,
+        start_y: 10,
+        width: 1,
+        height: 1,
+    };
+    set_roi(camera, roi).unwrap();
+    assert!(set_binning(camera, Binning::Bin2x2).is_err());
+    assert_eq!(get_binning(camera), Binning::Bin1x1);
+    assert_eq!(get_roi(camera).unwrap(), roi);
+}
+
+#[test]
+fn bit_depth_sets_readout_and_transfer() {
+    let mock = MockBackend::new();
+    let (_backend, camera) = open_mock_camera(&mock);
+    set_bit_depth(camera, BitDepth::Eight).unwrap();
+    assert!(mock.calls().contains(&"SetQHYCCDBitsMode"));
+    assert_eq!(mock.parameter(CameraFeature::ControlTransferBit), Some(8.0));
+
+    mock.clear_calls();
+    let error = set_bit_depth(camera, BitDepth::ThirtyTwo).unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<QHYError>(),
+        Some(QHYError::UnsupportedBitDepthError {
+            bit_depth: BitDepth::ThirtyTwo
+        })
+    ));
+    assert!(!mock.calls().contains(&"SetQHYCCDBitsMode"));
+    assert_eq!(mock.parameter(CameraFeature::ControlTransferBit), Some(8.0));
+}
+
+#[test]
+fn cycle_pump_hysteresis() {
+    let policy = CyclePumpPolicy {
+        threshold: 60.0,
+        hysteresis: 5.0,
+    };
+    // (pump running, humidity, pump running afterwards)
+    let cases = [
+        (false, 50.0, false),
+        (false, 60.0, false),
+        (false, 60.5, true),
+        // between the thresholds the pump keeps its state
+        (false, 57.0, false),
+        (true, 57.0, true),
+        (true, 55.0, true),
+        (true, 54.5, false),
+        (true, 70.0, true),
+    ];
+    for (running, humidity, expected) in cases {
+        let mock =
+            MockBackend::new().with_features(&[CameraFeature::ControlSensorChamberCyclePump]);
+        mock.set_parameter(
+            CameraFeature::ControlSensorChamberCyclePump,
+            if running { 1.0 } else { 0.0 },
+        );
+        mock.set_humidity(humidity);
+        let (_backend, camera) = open_mock_camera(&mock);
+
+        assert_eq!(
+            policy.apply(camera).unwrap(),
+            expected,
+            "{:?}",
+            (running, humidity)
+        );
+        assert_eq!(
+            mock.parameter(CameraFeature::ControlSensorChamberCyclePump),
+            Some(if expected { 1.0 } else { 0.0 })
+        );
+        assert_eq!(
+            mock.calls().contains(&"SetQHYCCDParam"),
+            running != expected,
+            "{:?}",
+            (running, humidity)
+        );
+    }
+}
+
+#[test]
+fn readout_mode_is_set_by_name() {
+    let mock = MockBackend::new().with_readout_modes(&[
+        ("STANDARD MODE", 3072, 2048),
+        ("High Gain Mode", 3072, 2048),
+        ("Extend Fullwell Mode", 3072, 2048),
+    ]);
+    let (_backend, camera) = open_mock_camera(&mock);
+
+    let mode = set_readout_mode_by_name(camera, "High Gain Mode").unwrap();
+    assert_eq!(
+        mode,
+        ReadoutMode {
+            id: 1,
+            name: "High Gain Mode".to_string(),
+            width: 3072,
+            height: 2048,
+        }
+    );
+    assert_eq!(get_readout_mode(camera).unwrap(), mode);
+
+    mock.clear_calls();
+    let error = set_readout_mode_by_name(camera, "Low Noise Mode").unwrap_err();
+    assert!(matches!(
+        error.downcast_ref::<QHYError>(),
+        Some(QHYError::ReadoutModeNotFoundError { name }) if name == "Low Noise Mode"
+    ));
+    assert!(!mock.calls().contains(&"SetQHYCCDReadMode"));
+    assert_eq!(get_readout_mode(camera).unwrap(), mode);
+}
+
+fn hdr_channel(samples: &[u16]) -> ImageData {
+    ImageData {
+        data: samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect(),
+        width: samples.len() as u32,
+        height: 1,
+        bits_per_pixel: 16,
+        channels: 1,
+    }
+}
+
+#[test]
+fn combine_hdr_channels_switches_at_the_threshold() {
+    let combine = HdrCombine {
+        threshold: 1000.0,
+        high_gain_scale: 1.0,
+        high_gain_offset: 0.0,
+        low_gain_scale: 4.0,
+        low_gain_offset: 10.0,
+    };
+    let combined = combine_hdr_channels(
+        &hdr_channel(&[100, 2000]),
+        &hdr_channel(&[25, 500]),
+        combine,
+    )
+    .unwrap();
+    assert_eq!(combined, hdr_channel(&[100, 2010]));
+
+    let mut odd = hdr_channel(&[100, 2000]);
+    odd.data.push(0);
+    assert!(combine_hdr_channels(&odd, &odd, combine).is_err());
+}
@@ -0,0 +1,98 @@
+//! Renders frames from `SensorModel` through the safe API
+use std::time::Duration;
+
+use libqhyccd_sys::{
+    get_image_size, get_single_frame, is_feature_supported, set_binning, set_bit_depth,
+    set_exposure, set_roi, start_single_frame_exposure, BackendGuard, BayerId, Binning, BitDepth,
+    CCDChipArea, CameraFeature, ImageData, MockBackend, QhyccdHandle, SensorModel, Star,
+};
+
+mod common;
+
+fn open_simulated_camera(mock: &MockBackend) -> (BackendGuard, QhyccdHandle) {
+    let (backend, camera) = common::open_first_camera(mock.clone());
+    set_exposure(camera, Duration::from_secs(10)).unwrap();
+    (backend, camera)
+}
+
+fn capture(camera: QhyccdHandle) -> ImageData {
+    start_single_frame_exposure(camera).unwrap();
+    get_single_frame(camera, get_image_size(camera).unwrap()).unwrap()
+}
+
+fn pixel(image: &ImageData, x: u32, y: u32) -> u16 {
+    let index = (y * image.width + x) as usize * 2;
+    u16::from_le_bytes([image.data[index], image.data[index + 1]])
+}
+
+#[test]
+fn frames_follow_roi_binning_and_bit_depth() {
+    let mock = MockBackend::new().with_sensor(SensorModel::default());
+    let (_backend, camera) = open_simulated_camera(&mock);
+
+    set_roi(
+        camera,
+        CCDChipArea {
+            start_x: 100,
+            start_y: 100,
+            width: 200,
+            height: 100,
+        },
+    )
+    .unwrap();
+    set_binning(camera, Binning::Bin2x2).unwrap();
+    let image = capture(camera);
+    assert_eq!(
+        (image.width, image.height, image.bits_per_pixel),
+        (100, 50, 16)
+    );
+    // bias plus sky and dark current of four pixels
+    assert!((1020.0..1080.0).contains(&pixel(&image, 50, 25).into()));
+
+    set_bit_depth(camera, BitDepth::Eight).unwrap();
+    let image = capture(camera);
+    assert_eq!(image.bits_per_pixel, 8);
+    assert!(image.data[..5000]
+        .iter()
+        .all(|value| (4..=6).contains(value)));
+}
+
+#[test]
+fn dark_current_depends_on_temperature() {
+    let sensor = SensorModel {
+        sky_background: 0.0,
+        dark_current: 1.0,
+        ..Default::default()
+    };
+    let mock = MockBackend::new().with_sensor(sensor);
+    let (_backend, camera) = open_simulated_camera(&mock);
+
+    let warm = capture(camera).mean();
+    mock.set_parameter(CameraFeature::ControlCurTemp, -5.0);
+    let cold = capture(camera).mean();
+    assert!(warm - cold > 8.0, "warm {} cold {}", warm, cold);
+}
+
+#[test]
+fn defocus_spreads_stars() {
+    let sensor = SensorModel {
+        stars: vec![Star {
+            x: 320.5,
+            y: 240.5,
+            flux: 10_000.0,
+        }],
+        bayer: Some(BayerId::BayerRg),
+        ..Default::default()
+    };
+    let mock = MockBackend::new().with_sensor(sensor);
+    let (_backend, camera) = open_simulated_camera(&mock);
+    assert_eq!(
+        is_feature_supported(camera, CameraFeature::CamColor).unwrap(),
+        BayerId::BayerRg as u32
+    );
+
+    let focused = pixel(&capture(camera), 320, 240);
+    mock.update_sensor(|sensor| sensor.defocus = 10.0);
+    let defocused = pixel(&capture(camera), 320, 240);
+    assert!(focused > 2 * defocused, "{} {}", focused, defocused);
+}
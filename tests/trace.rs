@@ -0,0 +1,72 @@
+//! Records the safe API running against `MockBackend` and replays the trace
+use std::env;
+
+use libqhyccd_sys::{
+    begin_live, get_camera_id, get_image_size, get_live_frame, init_sdk, scan_qhyccd, set_backend,
+    set_parameter, CameraFeature, ImageData, MockBackend, RecordingBackend, ReplayBackend,
+};
+
+mod common;
+
+fn session() -> (u32, ImageData) {
+    init_sdk().unwrap();
+    let cameras = scan_qhyccd().unwrap();
+    let camera = common::open(0);
+    set_parameter(camera, CameraFeature::ControlGain, 30.0).unwrap();
+    begin_live(camera).unwrap();
+    let size = get_image_size(camera).unwrap();
+    (cameras, get_live_frame(camera, size).unwrap())
+}
+
+#[test]
+fn replay_returns_recorded_responses() {
+    let path = env::temp_dir().join(format!("libqhyccd-sys-{}.trace", std::process::id()));
+    let mock = MockBackend::new();
+    mock.push_frame(ImageData {
+        data: vec![1, 2, 3, 4],
+        width: 2,
+        height: 1,
+        bits_per_pixel: 16,
+        channels: 1,
+    });
+    let recording = set_backend(RecordingBackend::wrap(mock, &path).unwrap());
+    let recorded = session();
+    drop(recording);
+
+    let replay = ReplayBackend::open(&path).unwrap();
+    let replaying = set_backend(replay.clone());
+    let replayed = session();
+    assert_eq!(replayed.0, recorded.0);
+    assert_eq!(replayed.1.width, 2);
+    assert_eq!(replayed.1.data[..4], recorded.1.data[..4]);
+    assert!(replay.mismatches().is_empty(), "{:?}", replay.mismatches());
+    assert!(replay.is_finished());
+    drop(replaying);
+
+    // a different parameter value does not match the trace
+    let replay = ReplayBackend::open(&path).unwrap();
+    let (replaying, camera) = common::open_first_camera(replay.clone());
+    assert!(set_parameter(camera, CameraFeature::ControlGain, 40.0).is_err());
+    assert_eq!(replay.mismatches().len(), 1);
+
+    drop(replaying);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn camera_id_that_is_not_utf8_is_an_error() {
+    let path = env::temp_dir().join(format!("libqhyccd-sys-{}-id.trace", std::process::id()));
+    let recording = set_backend(RecordingBackend::wrap(MockBackend::new(), &path).unwrap());
+    assert_eq!(get_camera_id(0).unwrap(), "QHY178M-mock0");
+    drop(recording);
+
+    let trace = std::fs::read_to_string(&path).unwrap();
+    std::fs::write(&path, trace.replace("\"QHY178M-mock0\"", "\"QHY178M-%FF\"")).unwrap();
+    let replay = ReplayBackend::open(&path).unwrap();
+    let replaying = set_backend(replay.clone());
+    assert!(get_camera_id(0).is_err());
+    assert!(replay.mismatches().is_empty(), "{:?}", replay.mismatches());
+
+    drop(replaying);
+    std::fs::remove_file(path).unwrap();
+}